@@ -0,0 +1,79 @@
+use euclid::primitives::Point;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// The datasets along with the radius of the searches, which is also the
+/// side of the grid's cells.
+fn datasets() -> Vec<(&'static str, usize, &'static str, f32)> {
+    vec![
+        ("uni", 10_000, "data/points10000uni.txt", 1.0),
+        ("uni", 100_000, "data/points100000uni.txt", 1.0),
+        ("norm", 10_000, "data/points10000norm.txt", 0.5),
+        ("norm", 100_000, "data/points100000norm.txt", 0.5),
+    ]
+}
+
+fn compare_radius_query(c: &mut Criterion) {
+    use euclid::grid::UniformGrid;
+    use euclid::kd_tree::KdTree;
+
+    let mut group = c.benchmark_group("Radius Query");
+
+    for (dist, i, filepath, radius) in datasets() {
+        let points = euclid::read_points(filepath);
+        // search around every 100th point of the dataset
+        let queries: Vec<Point<f32>> = points.iter().step_by(100).copied().collect();
+
+        let tree = KdTree::construct(points.clone());
+        group.bench_function(format!("kd-tree {} {}", dist, i), |b| {
+            b.iter(|| {
+                queries
+                    .iter()
+                    .map(|q| tree.within_radius(q, radius))
+                    .collect::<Vec<_>>()
+            })
+        });
+
+        let grid = UniformGrid::construct(points, radius);
+        group.bench_function(format!("grid {} {}", dist, i), |b| {
+            b.iter(|| {
+                queries
+                    .iter()
+                    .map(|q| grid.within_radius(q, radius))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+}
+
+fn compare_pairs_within(c: &mut Criterion) {
+    use euclid::grid::UniformGrid;
+    use euclid::kd_tree::KdTree;
+
+    let mut group = c.benchmark_group("Pairs Within");
+    group.sample_size(10);
+
+    for (dist, i, filepath, radius) in datasets() {
+        let points = euclid::read_points(filepath);
+
+        // the kd-tree searches around every point, finding each pair twice
+        let tree = KdTree::construct(points.clone());
+        group.bench_function(format!("kd-tree {} {}", dist, i), |b| {
+            b.iter(|| {
+                points
+                    .iter()
+                    .map(|p| tree.within_radius(p, radius).len() - 1)
+                    .sum::<usize>()
+                    / 2
+            })
+        });
+
+        let grid = UniformGrid::construct(points, radius);
+        group.bench_function(format!("grid {} {}", dist, i), |b| {
+            b.iter(|| grid.pairs_within(radius))
+        });
+    }
+}
+
+criterion_group!(benches, compare_radius_query, compare_pairs_within);
+criterion_main!(benches);
@@ -4,21 +4,23 @@ use criterion::{criterion_group, criterion_main, Criterion};
 
 fn compare_range_query(c: &mut Criterion) {
     use euclid::kd_tree::KdTree;
+    use euclid::layered_range_tree::LayeredRangeTree;
+    use euclid::quadtree::{PointQuadtree, PrQuadtree};
     use euclid::range_tree::RangeTree;
 
     let mut group = c.benchmark_group("Range Query");
 
-    // let sets = vec![
-    //     (100, "data/points100uni.txt"),
-    //     (1_000, "data/points1000uni.txt"),
-    //     (10_000, "data/points10000uni.txt"),
-    //     (65_536, "data/points65536uni.txt"),
-    //     (100_000, "data/points100000uni.txt"),
-    // ];
+    let uniform = vec![
+        (100, "data/points100uni.txt"),
+        (1_000, "data/points1000uni.txt"),
+        (10_000, "data/points10000uni.txt"),
+        (65_536, "data/points65536uni.txt"),
+        (100_000, "data/points100000uni.txt"),
+    ];
     // the region for the uniform distribution
-    // let region = Region::new(Point::from((1.0, 4.0)), Point::from((9.0, 19.0)));
+    let uniform_region = Region::new(Point::from((1.0, 4.0)), Point::from((9.0, 19.0)));
 
-    let sets = vec![
+    let normal = vec![
         (100, "data/points100norm.txt"),
         (1_000, "data/points1000norm.txt"),
         (10_000, "data/points10000norm.txt"),
@@ -26,24 +28,137 @@ fn compare_range_query(c: &mut Criterion) {
         (100_000, "data/points100000norm.txt"),
     ];
     // the region for the normal distribution
-    let region = Region::new(Point::from((10.0, 10.0)), Point::from((20.0, 20.0)));
+    let normal_region = Region::new(Point::from((10.0, 10.0)), Point::from((20.0, 20.0)));
 
     // let region = Region::new(Point::from((0.0, 0.0)), Point::from((100.0, 100.0)));
 
-    for (i, filepath) in &sets {
+    for (dist, sets, region) in [
+        ("uni", uniform, uniform_region),
+        ("norm", normal, normal_region),
+    ] {
+        for (i, filepath) in &sets {
+            let points = euclid::read_points(filepath);
+
+            let tree = KdTree::construct(points.clone());
+            group.bench_function(format!("kd-tree {} {}", dist, i), |b| {
+                b.iter(|| tree.range_query(&region))
+            });
+
+            let tree = RangeTree::construct(points.clone());
+            group.bench_function(format!("range-tree {} {}", dist, i), |b| {
+                b.iter(|| tree.range_query(&region))
+            });
+
+            let tree = LayeredRangeTree::construct(points.clone());
+            group.bench_function(format!("layered-range-tree {} {}", dist, i), |b| {
+                b.iter(|| tree.range_query(&region))
+            });
+
+            let tree = PointQuadtree::construct(points.clone());
+            group.bench_function(format!("point-quadtree {} {}", dist, i), |b| {
+                b.iter(|| tree.range_query(&region))
+            });
+
+            let tree = PrQuadtree::construct(points, 8, 16);
+            group.bench_function(format!("pr-quadtree {} {}", dist, i), |b| {
+                b.iter(|| tree.range_query(&region))
+            });
+        }
+    }
+}
+
+fn compare_three_sided_query(c: &mut Criterion) {
+    use euclid::priority_search_tree::{PrioritySearchTree, ThreeSided};
+    use euclid::range_tree::RangeTree;
+
+    let mut group = c.benchmark_group("Three-Sided Query");
+
+    let uniform = vec![
+        (100, "data/points100uni.txt"),
+        (1_000, "data/points1000uni.txt"),
+        (10_000, "data/points10000uni.txt"),
+        (65_536, "data/points65536uni.txt"),
+        (100_000, "data/points100000uni.txt"),
+    ];
+    let normal = vec![
+        (100, "data/points100norm.txt"),
+        (1_000, "data/points1000norm.txt"),
+        (10_000, "data/points10000norm.txt"),
+        (65_536, "data/points65536norm.txt"),
+        (100_000, "data/points100000norm.txt"),
+    ];
+
+    // the x-range and the lowest y-coordinate of the range for each distribution
+    for (dist, sets, (x0, x1, y)) in [
+        ("uni", uniform, (1.0, 9.0, 81.0)),
+        ("norm", normal, (10.0, 20.0, 10.0)),
+    ] {
+        // the range-tree answers the same query with a top at infinity
+        let region = Region::new(Point::from((x0, y)), Point::from((x1, f32::INFINITY)));
+        let range = ThreeSided::new(x0, x1, y);
+
+        for (i, filepath) in &sets {
+            let points = euclid::read_points(filepath);
+
+            let tree = RangeTree::construct(points.clone());
+            group.bench_function(format!("range-tree {} {}", dist, i), |b| {
+                b.iter(|| tree.range_query(&region))
+            });
+
+            let tree = PrioritySearchTree::construct(points);
+            group.bench_function(format!("priority-search-tree {} {}", dist, i), |b| {
+                b.iter(|| tree.range_query(&range))
+            });
+        }
+    }
+}
+
+fn compare_nearest_neighbour(c: &mut Criterion) {
+    use euclid::kd_tree::KdTree;
+    use euclid::quadtree::{PointQuadtree, PrQuadtree};
+
+    let mut group = c.benchmark_group("Nearest Neighbour");
+
+    let sets = vec![
+        ("uni", 10_000, "data/points10000uni.txt"),
+        ("uni", 100_000, "data/points100000uni.txt"),
+        ("norm", 10_000, "data/points10000norm.txt"),
+        ("norm", 100_000, "data/points100000norm.txt"),
+    ];
+
+    for (dist, i, filepath) in sets {
+        // a grid of query points over the dataset, including its sparse outskirts
+        let (lo, step) = match dist {
+            "uni" => (5.0, 10.0),
+            _ => (-45.0, 10.0),
+        };
+        let queries: Vec<Point<f32>> = (0..100)
+            .map(|j| Point::from((lo + (j % 10) as f32 * step, lo + (j / 10) as f32 * step)))
+            .collect();
+
         let points = euclid::read_points(filepath);
 
         let tree = KdTree::construct(points.clone());
-        group.bench_function(format!("kd-tree {}", i), |b| {
-            b.iter(|| tree.range_query(&region))
+        group.bench_function(format!("kd-tree {} {}", dist, i), |b| {
+            b.iter(|| queries.iter().map(|q| tree.nearest(q)).collect::<Vec<_>>())
+        });
+
+        let tree = PointQuadtree::construct(points.clone());
+        group.bench_function(format!("point-quadtree {} {}", dist, i), |b| {
+            b.iter(|| queries.iter().map(|q| tree.nearest(q)).collect::<Vec<_>>())
         });
 
-        let tree = RangeTree::construct(points);
-        group.bench_function(format!("range-tree {}", i), |b| {
-            b.iter(|| tree.range_query(&region))
+        let tree = PrQuadtree::construct(points, 8, 16);
+        group.bench_function(format!("pr-quadtree {} {}", dist, i), |b| {
+            b.iter(|| queries.iter().map(|q| tree.nearest(q)).collect::<Vec<_>>())
         });
     }
 }
 
-criterion_group!(benches, compare_range_query);
+criterion_group!(
+    benches,
+    compare_range_query,
+    compare_three_sided_query,
+    compare_nearest_neighbour
+);
 criterion_main!(benches);
@@ -0,0 +1,43 @@
+use euclid::primitives::Point;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+/// Generate randomly sampled points as input to benchmarks.
+fn sample_points(size: usize) -> Vec<Point<f32>> {
+    let mut points = Vec::new();
+    for _i in 0..size {
+        points.push(Point::from((
+            rand::random::<f32>() * 512.0,
+            rand::random::<f32>() * 512.0,
+        )));
+    }
+    points
+}
+
+fn construct_kd_tree(c: &mut Criterion) {
+    use euclid::kd_tree::{KdTree, SplitRule};
+
+    let mut group = c.benchmark_group("Kd-Tree Construction");
+    group.sample_size(10);
+
+    for i in [100_000, 1_000_000] {
+        let input = sample_points(i);
+        group.bench_function(format!("cycle {}", i), |b| {
+            b.iter_batched(
+                || input.clone(),
+                |points| KdTree::construct_with(points, SplitRule::Cycle),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(format!("max-spread {}", i), |b| {
+            b.iter_batched(
+                || input.clone(),
+                |points| KdTree::construct_with(points, SplitRule::MaxSpread),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+criterion_group!(benches, construct_kd_tree);
+criterion_main!(benches);
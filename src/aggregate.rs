@@ -0,0 +1,92 @@
+//! Project: euclid
+//! Module: aggregate
+//!
+//! This file contains the traits to summarize a set of points into a single
+//! value, which the spatial trees store for each subtree to answer aggregate
+//! range queries.
+
+/// A value with an associative operation to combine two values and an
+/// identity element for that operation.
+pub trait Monoid: Copy {
+    /// Returns the value that leaves any other value unchanged when combined.
+    fn identity() -> Self;
+
+    /// Combines two values, such that combining three values gives the same
+    /// result no matter how they are grouped (the operation is associative).
+    fn combine(self, other: Self) -> Self;
+}
+
+/// A user-defined summary of a set of points, computed by combining the weight
+/// of each point and its associated value of type `V`.
+///
+/// The empty tuple `()` is the aggregate that does not summarize anything.
+pub trait Aggregate<P, V = ()> {
+    type Value: Monoid;
+
+    /// Returns the weight contributed by the point `p` holding the value `v`.
+    fn weight(p: &P, v: &V) -> Self::Value;
+}
+
+/// The total of the weights.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Sum(pub f32);
+
+/// The lowest of the weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Min(pub f32);
+
+/// The highest of the weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Max(pub f32);
+
+impl Monoid for () {
+    fn identity() -> Self {}
+
+    fn combine(self, _: Self) -> Self {}
+}
+
+impl Monoid for Sum {
+    fn identity() -> Self {
+        Sum(0.0)
+    }
+
+    fn combine(self, other: Self) -> Self {
+        Sum(self.0 + other.0)
+    }
+}
+
+impl Monoid for Min {
+    fn identity() -> Self {
+        Min(f32::INFINITY)
+    }
+
+    fn combine(self, other: Self) -> Self {
+        Min(self.0.min(other.0))
+    }
+}
+
+impl Monoid for Max {
+    fn identity() -> Self {
+        Max(f32::NEG_INFINITY)
+    }
+
+    fn combine(self, other: Self) -> Self {
+        Max(self.0.max(other.0))
+    }
+}
+
+impl<A: Monoid, B: Monoid> Monoid for (A, B) {
+    fn identity() -> Self {
+        (A::identity(), B::identity())
+    }
+
+    fn combine(self, other: Self) -> Self {
+        (self.0.combine(other.0), self.1.combine(other.1))
+    }
+}
+
+impl<P, V> Aggregate<P, V> for () {
+    type Value = ();
+
+    fn weight(_: &P, _: &V) -> Self::Value {}
+}
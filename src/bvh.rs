@@ -0,0 +1,686 @@
+/// Project: euclid
+/// Module: bvh
+///
+/// This file contains functions to construct and query a bounding volume
+/// hierarchy (BVH) over line segments, which answers ray casting,
+/// closest-segment, and overlap queries.
+use crate::primitives::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::ops::Range;
+
+/// Index of a node within the tree's arena of nodes.
+type Child = Option<usize>;
+
+/// The largest number of segments stored by a leaf that is split by the
+/// median, and the number of segments below which a leaf is never split.
+const LEAF_SIZE: usize = 4;
+
+/// The largest number of segments a leaf may store when the surface area
+/// heuristic finds it cheaper than splitting.
+const MAX_LEAF_SIZE: usize = 16;
+
+/// The number of buckets that the centers of the segments are sorted into to
+/// evaluate the surface area heuristic.
+const BINS: usize = 12;
+
+/// The strategy for splitting the segments of a node between its children.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BuildRule {
+    /// Split at the median center of the segments along the axis where the
+    /// centers are spread the widest.
+    Median,
+    /// Split where the surface area heuristic (SAH) estimates the lowest cost
+    /// of a query, which in the plane weighs the number of segments on each
+    /// side by the perimeter of their bounding box (the chance of a random ray
+    /// hitting it).
+    #[default]
+    Sah,
+}
+
+/// A ray starting at an origin and extending infinitely in one direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    origin: Point<f32>,
+    dir: [f32; 2],
+}
+
+impl Ray {
+    /// Creates the ray starting at `origin` and passing through `through`.
+    pub fn new(origin: Point<f32>, through: Point<f32>) -> Self {
+        assert!(origin != through, "a ray requires a direction");
+        Self {
+            origin,
+            dir: [through.x() - origin.x(), through.y() - origin.y()],
+        }
+    }
+
+    /// Returns the point at the parameter `t` along the ray, where 1 is the
+    /// point the ray was created to pass through.
+    pub fn at(&self, t: f32) -> Point<f32> {
+        Point::from((
+            self.origin.x() + t * self.dir[0],
+            self.origin.y() + t * self.dir[1],
+        ))
+    }
+
+    /// Computes the parameter at which the ray enters the box `b` (0 if it
+    /// starts inside), if it hits the box at all.
+    fn enter(&self, b: &Bounds<2>) -> Option<f32> {
+        let (mut t0, mut t1) = (0.0, f32::INFINITY);
+        for (axis, o) in [self.origin.x(), self.origin.y()].into_iter().enumerate() {
+            let d = self.dir[axis];
+            // a ray parallel to the slab stays inside or outside of it
+            if d == 0.0 {
+                if o < b.min(axis) || o > b.max(axis) {
+                    return None;
+                }
+                continue;
+            }
+            let (a, c) = ((b.min(axis) - o) / d, (b.max(axis) - o) / d);
+            t0 = f32::max(t0, a.min(c));
+            t1 = f32::min(t1, a.max(c));
+            if t0 > t1 {
+                return None;
+            }
+        }
+        Some(t0)
+    }
+
+    /// Computes the parameter at which the ray first hits the segment `s`, if
+    /// it does.
+    fn hit(&self, s: &LineSegment) -> Option<f32> {
+        let (a, b) = (s.start(), s.end());
+        let cross = |u: [f32; 2], v: [f32; 2]| u[0] * v[1] - u[1] * v[0];
+        let d = self.dir;
+        let e = [b.x() - a.x(), b.y() - a.y()];
+        let w = [a.x() - self.origin.x(), a.y() - self.origin.y()];
+
+        let denom = cross(d, e);
+        if denom != 0.0 {
+            // solve origin + t * d = a + u * e
+            let t = cross(w, e) / denom;
+            let u = cross(w, d) / denom;
+            return match t >= 0.0 && (0.0..=1.0).contains(&u) {
+                true => Some(t),
+                false => None,
+            };
+        }
+        // the segment is parallel to the ray, so it is only hit when collinear
+        if cross(w, d) != 0.0 {
+            return None;
+        }
+        let dd = d[0] * d[0] + d[1] * d[1];
+        let ta = (w[0] * d[0] + w[1] * d[1]) / dd;
+        let tb = ta + (e[0] * d[0] + e[1] * d[1]) / dd;
+        match ta.max(tb) >= 0.0 {
+            true => Some(ta.min(tb).max(0.0)),
+            false => None,
+        }
+    }
+}
+
+/// Computes the bounding box of the segment `s`.
+fn bounds(s: &LineSegment) -> Bounds<2> {
+    Bounds::from(Region::from(s))
+}
+
+/// Computes the smallest box containing both `a` and `b`.
+fn union(a: &Bounds<2>, b: &Bounds<2>) -> Bounds<2> {
+    Bounds::new(
+        [a.min(0).min(b.min(0)), a.min(1).min(b.min(1))],
+        [a.max(0).max(b.max(0)), a.max(1).max(b.max(1))],
+    )
+}
+
+/// Computes half of the perimeter of the box `b`.
+fn margin(b: &Bounds<2>) -> f32 {
+    (b.max(0) - b.min(0)) + (b.max(1) - b.min(1))
+}
+
+/// Computes the distance from the point `p` to the closest point of the box
+/// `b`.
+fn box_dist(b: &Bounds<2>, p: &Point<f32>) -> f32 {
+    let dx = p.x() - p.x().clamp(b.min(0), b.max(0));
+    let dy = p.y() - p.y().clamp(b.min(1), b.max(1));
+    f32::sqrt(dx * dx + dy * dy)
+}
+
+/// Computes the distance from the point `p` to the closest point of the
+/// segment `s`.
+fn segment_dist(s: &LineSegment, p: &Point<f32>) -> f32 {
+    let (a, b) = (s.start(), s.end());
+    let (dx, dy) = (b.x() - a.x(), b.y() - a.y());
+    let len = dx * dx + dy * dy;
+    let t = match len > 0.0 {
+        true => (((p.x() - a.x()) * dx + (p.y() - a.y()) * dy) / len).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    euclid_dist(p, &Point::from((a.x() + t * dx, a.y() + t * dy)))
+}
+
+/// Checks if the segment `s` shares at least one point with the rectangle
+/// defined by `region` (including the boundary).
+fn segment_overlaps(s: &LineSegment, region: &Region<f32>) -> bool {
+    if region.contains_point(s.start()) || region.contains_point(s.end()) {
+        return true;
+    }
+    let corners = [
+        Point::from((region.l_x(), region.l_y())),
+        Point::from((region.r_x(), region.l_y())),
+        Point::from((region.r_x(), region.r_y())),
+        Point::from((region.l_x(), region.r_y())),
+    ];
+    (0..4).any(|i| segments_intersect(s, &LineSegment::from((corners[i], corners[(i + 1) % 4]))))
+}
+
+#[derive(Debug, PartialEq)]
+struct Node {
+    // the bounding box of every segment below the node
+    bounds: Bounds<2>,
+    left: Child,
+    right: Child,
+    // the position of a leaf's segments within the list of items
+    start: usize,
+    len: usize,
+}
+
+impl Node {
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+
+    /// Returns the positions of the leaf's segments within the list of items.
+    fn items(&self) -> Range<usize> {
+        self.start..self.start + self.len
+    }
+}
+
+/// A node or a segment waiting to be visited during a closest-segment search,
+/// ordered so that the closest one is popped first from a max-heap.
+struct Candidate {
+    // lower bound on the distance to a node, or the distance to a segment
+    dist: f32,
+    node: usize,
+    item: Option<usize>,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.dist == other.dist
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.dist.partial_cmp(&self.dist).unwrap()
+    }
+}
+
+/// A bounding volume hierarchy over line segments in the plane.
+///
+/// Every node stores the bounding box of its segments, which are split between
+/// its two children by `BuildRule`, and the leaves store a few segments each.
+/// Unlike a kd-tree, the segments are partitioned rather than space, so the
+/// boxes of siblings may overlap, but every segment is stored exactly once.
+///
+/// The segments of a leaf are stored next to each other, so a query reads them
+/// in order. Each segment holds a value of type `V`, such as the index of the
+/// polygon it is an edge of.
+#[derive(Debug, PartialEq)]
+pub struct Bvh<V = ()> {
+    items: Vec<(LineSegment, V)>,
+    nodes: Vec<Node>,
+    root: Child,
+}
+
+impl Bvh {
+    /// Builds a BVH for the set of known `segments`.
+    pub fn construct(segments: Vec<LineSegment>, rule: BuildRule) -> Self {
+        Self::construct_items(segments.into_iter().map(|s| (s, ())).collect(), rule)
+    }
+}
+
+impl Bvh<usize> {
+    /// Builds a BVH over the edges of the `polygons`, given by their vertices
+    /// in order, where each edge holds the index of its polygon.
+    pub fn construct_polygons(polygons: &[Vec<Point<f32>>], rule: BuildRule) -> Self {
+        let edges = polygons
+            .iter()
+            .enumerate()
+            .flat_map(|(id, vertices)| {
+                (0..vertices.len()).map(move |i| {
+                    let (p, q) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+                    (LineSegment::from((p, q)), id)
+                })
+            })
+            .collect();
+        Self::construct_items(edges, rule)
+    }
+}
+
+impl<V> Bvh<V> {
+    /// Builds a BVH for the set of known segments holding values `items`.
+    ///
+    /// - Time complexity: O(n log(n))
+    pub fn construct_items(items: Vec<(LineSegment, V)>, rule: BuildRule) -> Self {
+        let boxes: Vec<Bounds<2>> = items.iter().map(|(s, _)| bounds(s)).collect();
+        let centers: Vec<[f32; 2]> = boxes
+            .iter()
+            .map(|b| [(b.min(0) + b.max(0)) / 2.0, (b.min(1) + b.max(1)) / 2.0])
+            .collect();
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        let mut nodes = Vec::with_capacity(2 * items.len().div_ceil(LEAF_SIZE));
+        let root = match items.is_empty() {
+            true => None,
+            false => Some(Self::build(
+                &mut nodes, &boxes, &centers, &mut order, 0, rule,
+            )),
+        };
+
+        // store the segments in the order of the leaves
+        let mut slots: Vec<Option<(LineSegment, V)>> = items.into_iter().map(Some).collect();
+        let items = order.iter().map(|&i| slots[i].take().unwrap()).collect();
+        Self { items, nodes, root }
+    }
+
+    /// Recursive function call to build the subtree over the segments at the
+    /// indices `order`, which start at position `start` of the list of items.
+    fn build(
+        nodes: &mut Vec<Node>,
+        boxes: &[Bounds<2>],
+        centers: &[[f32; 2]],
+        order: &mut [usize],
+        start: usize,
+        rule: BuildRule,
+    ) -> usize {
+        let bounds = order[1..]
+            .iter()
+            .fold(boxes[order[0]], |acc, &i| union(&acc, &boxes[i]));
+        let id = nodes.len();
+        nodes.push(Node {
+            bounds,
+            left: None,
+            right: None,
+            start,
+            len: order.len(),
+        });
+        if order.len() <= LEAF_SIZE {
+            return id;
+        }
+
+        // the axis where the centers are spread the widest
+        let spread = |axis: usize| {
+            order
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &i| {
+                    (lo.min(centers[i][axis]), hi.max(centers[i][axis]))
+                })
+        };
+        let (axis, (lo, hi)) = match (spread(0), spread(1)) {
+            (x, y) if x.1 - x.0 >= y.1 - y.0 => (0, x),
+            (_, y) => (1, y),
+        };
+        let split = match (rule, hi > lo) {
+            (BuildRule::Sah, true) => Self::sah_split(boxes, centers, order, axis, lo, hi),
+            _ => None,
+        };
+        let middle = match split {
+            Some(middle) => middle,
+            None if rule == BuildRule::Sah && hi > lo && order.len() <= MAX_LEAF_SIZE => {
+                return id;
+            }
+            // identical centers can only be split by index
+            None => {
+                let middle = order.len() / 2;
+                order.select_nth_unstable_by(middle, |&a, &b| {
+                    centers[a][axis].partial_cmp(&centers[b][axis]).unwrap()
+                });
+                middle
+            }
+        };
+
+        let (left, right) = order.split_at_mut(middle);
+        let left = Self::build(nodes, boxes, centers, left, start, rule);
+        let right = Self::build(nodes, boxes, centers, right, start + middle, rule);
+        nodes[id].left = Some(left);
+        nodes[id].right = Some(right);
+        id
+    }
+
+    /// Sorts the centers of the segments at the indices `order` into buckets
+    /// along `axis` (spanning from `lo` to `hi`) to find the cheapest split
+    /// between the buckets by the surface area heuristic.
+    ///
+    /// Partitions `order` by that split to return the number of segments on
+    /// the lower side, or `None` if storing every segment in a leaf is cheaper.
+    fn sah_split(
+        boxes: &[Bounds<2>],
+        centers: &[[f32; 2]],
+        order: &mut [usize],
+        axis: usize,
+        lo: f32,
+        hi: f32,
+    ) -> Option<usize> {
+        let bin =
+            |i: usize| (((centers[i][axis] - lo) / (hi - lo) * BINS as f32) as usize).min(BINS - 1);
+        let mut bins: [(usize, Option<Bounds<2>>); BINS] = [(0, None); BINS];
+        for &i in order.iter() {
+            let (count, cover) = &mut bins[bin(i)];
+            *count += 1;
+            *cover = Some(cover.map_or(boxes[i], |c| union(&c, &boxes[i])));
+        }
+
+        // the number of segments and the margin of their box on each side
+        let sweep = |range: &mut dyn Iterator<Item = &(usize, Option<Bounds<2>>)>| {
+            let mut acc: (usize, Option<Bounds<2>>) = (0, None);
+            range
+                .map(|(count, cover)| {
+                    acc.0 += count;
+                    if let Some(c) = cover {
+                        acc.1 = Some(acc.1.map_or(*c, |a| union(&a, c)));
+                    }
+                    (acc.0, acc.1.map_or(0.0, |b| margin(&b)))
+                })
+                .collect::<Vec<_>>()
+        };
+        let below = sweep(&mut bins.iter());
+        let mut above = sweep(&mut bins.iter().rev());
+        above.reverse();
+
+        let (split, cost) = (1..BINS)
+            .filter(|&k| below[k - 1].0 > 0 && above[k].0 > 0)
+            .map(|k| {
+                let cost = below[k - 1].0 as f32 * below[k - 1].1 + above[k].0 as f32 * above[k].1;
+                (k, cost)
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())?;
+
+        // a query pays 1 to visit the node and 1 for every segment it checks
+        let total = below[BINS - 1].1;
+        if total > 0.0 && 1.0 + cost / total >= order.len() as f32 && order.len() <= MAX_LEAF_SIZE {
+            return None;
+        }
+        let mut middle = 0;
+        for i in 0..order.len() {
+            if bin(order[i]) < split {
+                order.swap(i, middle);
+                middle += 1;
+            }
+        }
+        Some(middle)
+    }
+
+    /// Returns the number of segments stored in the hierarchy.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if the hierarchy stores no segments.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Casts the `ray` to find the first segment it hits, returning the point
+    /// where it hits along with the segment and its value.
+    ///
+    /// The nodes are visited front to back, and a node is skipped once the ray
+    /// enters its box beyond the closest hit so far.
+    pub fn first_hit(&self, ray: &Ray) -> Option<(Point<f32>, &LineSegment, &V)> {
+        let mut best: Option<(f32, usize)> = None;
+        let mut stack: Vec<(usize, f32)> = self
+            .root
+            .and_then(|root| Some((root, ray.enter(&self.nodes[root].bounds)?)))
+            .into_iter()
+            .collect();
+        while let Some((id, t)) = stack.pop() {
+            if best.is_some_and(|(b, _)| t > b) {
+                continue;
+            }
+            let node = &self.nodes[id];
+            if node.is_leaf() {
+                for i in node.items() {
+                    if let Some(t) = ray.hit(&self.items[i].0) {
+                        if best.is_none_or(|(b, _)| t < b) {
+                            best = Some((t, i));
+                        }
+                    }
+                }
+                continue;
+            }
+            let mut children: Vec<(usize, f32)> = [node.left, node.right]
+                .into_iter()
+                .flatten()
+                .filter_map(|c| Some((c, ray.enter(&self.nodes[c].bounds)?)))
+                .collect();
+            // the nearer child is popped first
+            children.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+            stack.extend(children);
+        }
+        best.map(|(t, i)| (ray.at(t), &self.items[i].0, &self.items[i].1))
+    }
+
+    /// Finds the segment closest to the point `p` along with its value.
+    ///
+    /// The nodes and segments are visited in order of increasing distance
+    /// (best-first), so the search stops at the first segment popped.
+    pub fn closest_segment(&self, p: &Point<f32>) -> Option<(&LineSegment, &V)> {
+        let mut heap = BinaryHeap::new();
+        if let Some(root) = self.root {
+            heap.push(Candidate {
+                dist: box_dist(&self.nodes[root].bounds, p),
+                node: root,
+                item: None,
+            });
+        }
+        while let Some(c) = heap.pop() {
+            if let Some(i) = c.item {
+                return Some((&self.items[i].0, &self.items[i].1));
+            }
+            let node = &self.nodes[c.node];
+            match node.is_leaf() {
+                true => heap.extend(node.items().map(|i| Candidate {
+                    dist: segment_dist(&self.items[i].0, p),
+                    node: c.node,
+                    item: Some(i),
+                })),
+                false => heap.extend([node.left, node.right].into_iter().flatten().map(|child| {
+                    Candidate {
+                        dist: box_dist(&self.nodes[child].bounds, p),
+                        node: child,
+                        item: None,
+                    }
+                })),
+            }
+        }
+        None
+    }
+
+    /// Finds all segments sharing at least one point with the rectangle
+    /// defined by `region` (including the boundary) along with their values.
+    pub fn overlap_query(&self, region: &Region<f32>) -> Vec<(&LineSegment, &V)> {
+        let q = Bounds::from(*region);
+        let mut result = Vec::new();
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id];
+            if !node.bounds.intersects(&q) {
+                continue;
+            }
+            match node.is_leaf() {
+                true => result.extend(
+                    self.items[node.items()]
+                        .iter()
+                        .filter(|(s, _)| segment_overlaps(s, region))
+                        .map(|(s, v)| (s, v)),
+                ),
+                false => stack.extend([node.left, node.right].into_iter().flatten()),
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Samples random segments of varying lengths, with some axis-aligned and
+    /// degenerate ones.
+    fn sample_segments(rng: &mut impl Rng, size: usize) -> Vec<LineSegment> {
+        (0..size)
+            .map(|_| {
+                let p = sample_point(rng, 0.0..100.0);
+                let (dx, dy) = (
+                    rng.gen::<f32>() * 20.0 - 10.0,
+                    rng.gen::<f32>() * 20.0 - 10.0,
+                );
+                let q = match rng.gen_range(0..8) {
+                    0 => p,
+                    1 => Point::from((p.x() + dx, p.y())),
+                    2 => Point::from((p.x(), p.y() + dy)),
+                    _ => Point::from((p.x() + dx, p.y() + dy)),
+                };
+                LineSegment::from((p, q))
+            })
+            .collect()
+    }
+
+    /// Copies the segments (which do not implement `Clone`).
+    fn copied(segments: &[LineSegment]) -> Vec<LineSegment> {
+        segments
+            .iter()
+            .map(|s| LineSegment::from((*s.start(), *s.end())))
+            .collect()
+    }
+
+    #[test]
+    fn ut_ray_hit() {
+        let ray = Ray::new(Point::from((0.0, 0.0)), Point::from((1.0, 0.0)));
+        let crossing = LineSegment::from((Point::from((3.0, -1.0)), Point::from((3.0, 1.0))));
+        assert_eq!(ray.hit(&crossing), Some(3.0));
+        let behind = LineSegment::from((Point::from((-3.0, -1.0)), Point::from((-3.0, 1.0))));
+        assert_eq!(ray.hit(&behind), None);
+        let parallel = LineSegment::from((Point::from((2.0, 1.0)), Point::from((5.0, 1.0))));
+        assert_eq!(ray.hit(&parallel), None);
+        // a collinear segment is hit at its nearest point ahead of the origin
+        let collinear = LineSegment::from((Point::from((6.0, 0.0)), Point::from((4.0, 0.0))));
+        assert_eq!(ray.hit(&collinear), Some(4.0));
+        let around = LineSegment::from((Point::from((-1.0, 0.0)), Point::from((1.0, 0.0))));
+        assert_eq!(ray.hit(&around), Some(0.0));
+
+        let bvh = Bvh::construct(vec![crossing, behind, parallel, collinear], BuildRule::Sah);
+        let (p, s, _) = bvh.first_hit(&ray).unwrap();
+        assert_eq!(p, Point::from((3.0, 0.0)));
+        assert_eq!(
+            s,
+            &LineSegment::from((Point::from((3.0, -1.0)), Point::from((3.0, 1.0))))
+        );
+    }
+
+    #[test]
+    fn ut_queries_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let segments = sample_segments(&mut rng, 2_000);
+        for rule in [BuildRule::Median, BuildRule::Sah] {
+            let bvh = Bvh::construct(copied(&segments), rule);
+            assert_eq!(bvh.len(), segments.len());
+            for _ in 0..100 {
+                let ray = Ray::new(
+                    sample_point(&mut rng, -10.0..110.0),
+                    sample_point(&mut rng, -10.0..110.0),
+                );
+                let expected = segments
+                    .iter()
+                    .filter_map(|s| ray.hit(s))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap());
+                let output = bvh.first_hit(&ray).map(|(_, s, _)| ray.hit(s).unwrap());
+                assert_eq!(output, expected);
+
+                let p = sample_point(&mut rng, -10.0..110.0);
+                let expected = segments
+                    .iter()
+                    .map(|s| segment_dist(s, &p))
+                    .fold(f32::INFINITY, f32::min);
+                let (s, _) = bvh.closest_segment(&p).unwrap();
+                assert_eq!(segment_dist(s, &p), expected);
+
+                let region = sample_region(&mut rng, -10.0..110.0);
+                let mut expected: Vec<&LineSegment> = segments
+                    .iter()
+                    .filter(|s| segment_overlaps(s, &region))
+                    .collect();
+                let mut output: Vec<&LineSegment> = bvh
+                    .overlap_query(&region)
+                    .into_iter()
+                    .map(|(s, _)| s)
+                    .collect();
+                for list in [&mut expected, &mut output] {
+                    list.sort_by(|a, b| {
+                        (a.start(), a.end())
+                            .partial_cmp(&(b.start(), b.end()))
+                            .unwrap()
+                    });
+                }
+                assert_eq!(output, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn ut_polygons() {
+        let square = vec![
+            Point::from((0.0, 0.0)),
+            Point::from((4.0, 0.0)),
+            Point::from((4.0, 4.0)),
+            Point::from((0.0, 4.0)),
+        ];
+        let triangle = vec![
+            Point::from((6.0, 0.0)),
+            Point::from((9.0, 0.0)),
+            Point::from((6.0, 3.0)),
+        ];
+        let bvh = Bvh::construct_polygons(&[square, triangle], BuildRule::Median);
+        assert_eq!(bvh.len(), 7);
+
+        // cast from inside the square towards the triangle
+        let ray = Ray::new(Point::from((2.0, 1.0)), Point::from((3.0, 1.0)));
+        assert_eq!(
+            bvh.first_hit(&ray).map(|(p, _, &id)| (p, id)),
+            Some((Point::from((4.0, 1.0)), 0))
+        );
+        let ray = Ray::new(Point::from((5.0, 1.0)), Point::from((6.0, 1.0)));
+        assert_eq!(bvh.first_hit(&ray).map(|(_, _, &id)| id), Some(1));
+        let ray = Ray::new(Point::from((5.0, 1.0)), Point::from((5.0, 2.0)));
+        assert_eq!(bvh.first_hit(&ray), None);
+
+        assert_eq!(
+            bvh.closest_segment(&Point::from((8.0, 2.0)))
+                .map(|(_, &id)| id),
+            Some(1)
+        );
+        let region = Region::new(Point::from((3.0, 3.0)), Point::from((6.5, 3.5)));
+        let mut ids: Vec<usize> = bvh
+            .overlap_query(&region)
+            .into_iter()
+            .map(|(_, &id)| id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![0, 1, 1]);
+
+        let empty = Bvh::construct(Vec::new(), BuildRule::Sah);
+        assert!(empty.is_empty());
+        assert_eq!(empty.first_hit(&ray), None);
+        assert_eq!(empty.closest_segment(&Point::new()), None);
+    }
+}
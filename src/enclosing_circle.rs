@@ -0,0 +1,205 @@
+/// Project: euclid
+/// Module: enclosing_circle
+///
+/// This file contains geometric algorithms for computing the smallest circle
+/// that encloses a given set of points.
+use crate::primitives::*;
+use rand::seq::SliceRandom;
+
+/// The relative amount by which the boundary of a circle is widened when
+/// checking if it encloses a point.
+const TOLERANCE: f32 = 1e-5;
+
+/// Checks if the circle `c` encloses the point `p`.
+///
+/// A circle built from points on its boundary does not pass through them
+/// exactly after rounding, so the radius is widened by a small tolerance
+/// relative to its length.
+fn encloses(c: &Circle, p: &Point<f32>) -> bool {
+    euclid_dist(c.center(), p) <= c.radius() * (1.0 + TOLERANCE)
+}
+
+/// Computes the smallest enclosing circle for a set of 2-dimensional `points`
+/// by testing every circle defined by two or three of the points.
+///
+/// Returns `None` when there are no points.
+///
+/// - Time complexity: O(n^4)
+pub fn brute_force(points: &[Point<f32>]) -> Option<Circle> {
+    let encloses_all = |c: &Circle| points.iter().all(|p| encloses(c, p));
+
+    let mut best: Option<Circle> = None;
+    let mut consider = |c: Circle| {
+        if best.is_none_or(|b| c.radius() < b.radius()) && encloses_all(&c) {
+            best = Some(c);
+        }
+    };
+    for (i, p) in points.iter().enumerate() {
+        consider(Circle::new(*p, 0.0));
+        for (j, q) in points.iter().enumerate().skip(i + 1) {
+            consider(Circle::from_diameter(p, q));
+            for r in points.iter().skip(j + 1) {
+                if let Some(c) = Circle::circumscribe(p, q, r) {
+                    consider(c);
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Computes the smallest enclosing circle for a set of 2-dimensional `points`
+/// using Welzl's randomized incremental algorithm.
+///
+/// Returns `None` when there are no points. Collinear triples on the boundary
+/// fall back to the circle spanned by their two farthest points.
+///
+/// - Time complexity: O(n) expected
+pub fn welzl(points: &[Point<f32>]) -> Option<Circle> {
+    let points: Vec<&Point<f32>> = points.iter().collect();
+    welzl_refs(points)
+}
+
+/// Computes the smallest enclosing circle for the convex polygon `polygon`,
+/// such as the output of `convex_hull::upper_lower`.
+///
+/// Only the vertices of a convex polygon can touch its smallest enclosing
+/// circle, so the polygon's vertices are the entire input.
+///
+/// - Time complexity: O(h) expected, for h vertices
+pub fn convex_polygon(polygon: &[&Point<f32>]) -> Option<Circle> {
+    welzl_refs(polygon.to_vec())
+}
+
+/// Runs Welzl's algorithm over the points `points` (in place of recursion, the
+/// boundary points are fixed by nested loops over a random permutation).
+fn welzl_refs(mut points: Vec<&Point<f32>>) -> Option<Circle> {
+    // compute random permutation of the points
+    points.shuffle(&mut rand::thread_rng());
+
+    let mut circle = Circle::new(**points.first()?, 0.0);
+    for i in 1..points.len() {
+        if encloses(&circle, points[i]) {
+            continue;
+        }
+        // p_i must lie on the boundary of the circle for the first i points
+        circle = Circle::new(*points[i], 0.0);
+        for j in 0..i {
+            if encloses(&circle, points[j]) {
+                continue;
+            }
+            // p_i and p_j must both lie on the boundary
+            circle = Circle::from_diameter(points[i], points[j]);
+            for k in 0..j {
+                if encloses(&circle, points[k]) {
+                    continue;
+                }
+                // p_i, p_j, and p_k define the boundary
+                circle = boundary_circle(points[i], points[j], points[k]);
+            }
+        }
+    }
+    Some(circle)
+}
+
+/// Creates the smallest circle with `p`, `q`, and `r` on or inside its boundary
+/// when all three points are required to define it.
+fn boundary_circle(p: &Point<f32>, q: &Point<f32>, r: &Point<f32>) -> Circle {
+    match Circle::circumscribe(p, q, r) {
+        Some(c) => c,
+        // the points are collinear: span the two farthest points
+        None => [(p, q), (p, r), (q, r)]
+            .into_iter()
+            .map(|(a, b)| Circle::from_diameter(a, b))
+            .fold(Circle::new(*p, 0.0), |a, b| {
+                if b.radius() > a.radius() {
+                    b
+                } else {
+                    a
+                }
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convex_hull::upper_lower;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn assert_smallest(points: &[Point<f32>], circle: Circle) {
+        let expected = brute_force(points).unwrap();
+        assert!(points.iter().all(|p| encloses(&circle, p)));
+        assert!((circle.radius() - expected.radius()).abs() <= 1e-3 * (1.0 + expected.radius()));
+    }
+
+    #[test]
+    fn ut_welzl() {
+        let input = vec![
+            Point::from((0.0, 0.0)),
+            Point::from((2.0, 10.0)),
+            Point::from((5.0, 1.0)),
+            Point::from((2.0, 4.0)),
+            Point::from((3.0, 2.0)),
+        ];
+
+        let output = welzl(&input).unwrap();
+
+        let expected = Circle::circumscribe(&input[0], &input[1], &input[2]).unwrap();
+        assert!((output.radius() - expected.radius()).abs() < 1e-4);
+        assert!(euclid_dist(output.center(), expected.center()) < 1e-4);
+        assert_eq!(welzl(&[]), None);
+        assert_eq!(
+            welzl(&[Point::from((1.0, 2.0))]),
+            Some(Circle::new(Point::from((1.0, 2.0)), 0.0))
+        );
+    }
+
+    #[test]
+    fn ut_welzl_degenerate() {
+        // collinear points
+        let input: Vec<Point<f32>> = (0..20)
+            .map(|i| Point::from((i as f32, 2.0 * i as f32 + 1.0)))
+            .collect();
+        assert_smallest(&input, welzl(&input).unwrap());
+
+        // cocircular points
+        let input: Vec<Point<f32>> = (0..24)
+            .map(|i| {
+                let angle = i as f32 * std::f32::consts::PI / 12.0;
+                Point::from((3.0 + 5.0 * angle.cos(), -1.0 + 5.0 * angle.sin()))
+            })
+            .collect();
+        assert_smallest(&input, welzl(&input).unwrap());
+
+        // duplicate points
+        let input = vec![Point::from((4.0, 4.0)); 6];
+        assert_eq!(welzl(&input).unwrap().radius(), 0.0);
+    }
+
+    #[test]
+    fn ut_welzl_random() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for size in [2, 3, 5, 10, 40] {
+            let input: Vec<Point<f32>> = (0..size)
+                .map(|_| Point::from((rng.gen::<f32>() * 512.0, rng.gen::<f32>() * 512.0)))
+                .collect();
+            assert_smallest(&input, welzl(&input).unwrap());
+        }
+        // many duplicates on a coarse grid
+        let input: Vec<Point<f32>> = (0..40)
+            .map(|_| Point::from((rng.gen_range(0..5) as f32, rng.gen_range(0..5) as f32)))
+            .collect();
+        assert_smallest(&input, welzl(&input).unwrap());
+    }
+
+    #[test]
+    fn ut_convex_polygon() {
+        let input = crate::read_points("tests/convex_hull/ec1.txt");
+        let hull = upper_lower(&input);
+
+        let output = convex_polygon(&hull).unwrap();
+
+        assert_smallest(&input, output);
+    }
+}
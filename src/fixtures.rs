@@ -0,0 +1,52 @@
+/// Project: euclid
+/// Module: fixtures
+///
+/// This file contains the inputs shared by the tests of several modules.
+use crate::primitives::*;
+use rand::Rng;
+use std::ops::Range;
+
+/// Samples a point whose coordinates are both spread uniformly over `range`.
+pub fn sample_point(rng: &mut impl Rng, range: Range<f32>) -> Point<f32> {
+    let (lo, side) = (range.start, range.end - range.start);
+    Point::from((lo + rng.gen::<f32>() * side, lo + rng.gen::<f32>() * side))
+}
+
+/// Samples a rectangle whose corners are both spread uniformly over `range`.
+pub fn sample_region(rng: &mut impl Rng, range: Range<f32>) -> Region<f32> {
+    Region::new(sample_point(rng, range.clone()), sample_point(rng, range))
+}
+
+/// Returns sets of points with heavily duplicated and grid-aligned
+/// coordinates: a single repeated point, points on a vertical and on a
+/// horizontal line, and points on a small grid.
+pub fn degenerate_points(rng: &mut impl Rng, size: usize) -> Vec<Vec<Point<f32>>> {
+    let mut sample = |f: &dyn Fn(f32, f32) -> (f32, f32)| -> Vec<Point<f32>> {
+        (0..size)
+            .map(|_| Point::from(f(rng.gen_range(0..3) as f32, rng.gen_range(0..3) as f32)))
+            .collect()
+    };
+    vec![
+        sample(&|_, _| (1.0, 1.0)),
+        sample(&|_, y| (1.0, y)),
+        sample(&|x, _| (x, 1.0)),
+        sample(&|x, y| (x, y)),
+    ]
+}
+
+/// Returns every rectangle whose corners lie on the grid used by
+/// `degenerate_points`, including the lines just outside of it.
+pub fn grid_regions() -> Vec<Region<f32>> {
+    let values = [-1.0, 0.0, 0.5, 1.0, 2.0, 3.0];
+    let mut regions = Vec::new();
+    for &x0 in &values {
+        for &x1 in &values {
+            for &y0 in &values {
+                for &y1 in &values {
+                    regions.push(Region::new(Point::from((x0, y0)), Point::from((x1, y1))));
+                }
+            }
+        }
+    }
+    regions
+}
@@ -0,0 +1,392 @@
+/// Project: euclid
+/// Module: grid
+///
+/// This file contains functions to construct and query a uniform grid (spatial
+/// hash) of points, which answers fixed-radius neighbour searches.
+use crate::primitives::*;
+use std::collections::HashMap;
+
+/// The coordinates of a cell within the grid.
+type Cell = (i64, i64);
+
+/// A stable reference to a point inserted into a grid, used to move or remove
+/// that exact point later on.
+///
+/// A handle is invalidated once its point is removed, after which its slot may
+/// be handed out again by a later insertion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle(usize);
+
+/// A uniform grid over points in the plane.
+///
+/// The plane is divided into square cells of side `cell_size`, and every point
+/// is stored in the bucket of the cell containing it. Only the cells holding
+/// points are kept (in a hash map keyed by the cell's coordinates), so the
+/// points may spread out arbitrarily far.
+///
+/// A search within a radius `r` only visits the cells overlapping the square
+/// around the query point, which takes O(k) time when `r` is close to the cell
+/// size and the points are evenly spread out. Clustered points fill a few
+/// buckets instead, where a `KdTree` adapts better.
+#[derive(Debug, PartialEq)]
+pub struct UniformGrid<V = ()> {
+    cell_size: f32,
+    // the slots of the points within each non-empty cell
+    cells: HashMap<Cell, Vec<usize>>,
+    // the points along with their values (taken once removed)
+    items: Vec<Option<(Point<f32>, V)>>,
+    // slots of the removed points
+    free: Vec<usize>,
+}
+
+impl UniformGrid {
+    /// Builds a grid with cells of side `cell_size` for the set of known
+    /// points `p`, whose handles are `Handle(0)`, `Handle(1)`, and so on.
+    pub fn construct(points: Vec<Point<f32>>, cell_size: f32) -> Self {
+        Self::construct_items(points.into_iter().map(|p| (p, ())).collect(), cell_size)
+    }
+
+    /// Inserts the point `p` into the grid to return its handle.
+    pub fn insert(&mut self, p: Point<f32>) -> Handle {
+        self.insert_item(p, ())
+    }
+}
+
+impl<V> UniformGrid<V> {
+    /// Creates an empty grid with cells of side `cell_size`.
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "the cells must have a positive size");
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            items: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Builds a grid with cells of side `cell_size` for the set of known
+    /// points holding values `items`.
+    ///
+    /// - Time complexity: O(n)
+    pub fn construct_items(items: Vec<(Point<f32>, V)>, cell_size: f32) -> Self {
+        let mut grid = Self::new(cell_size);
+        for (p, value) in items {
+            grid.insert_item(p, value);
+        }
+        grid
+    }
+
+    /// Returns the coordinates of the cell containing the point `p`.
+    fn cell(&self, p: &Point<f32>) -> Cell {
+        (
+            (p.x() / self.cell_size).floor() as i64,
+            (p.y() / self.cell_size).floor() as i64,
+        )
+    }
+
+    /// Returns the number of points stored in the grid.
+    pub fn len(&self) -> usize {
+        self.items.len() - self.free.len()
+    }
+
+    /// Checks if the grid stores no points.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the point referred to by the handle `h` along with its value
+    /// (if it was not removed).
+    pub fn get(&self, h: Handle) -> Option<(&Point<f32>, &V)> {
+        self.items.get(h.0)?.as_ref().map(|(p, v)| (p, v))
+    }
+
+    /// Inserts the point `p` holding `value` into the grid to return its
+    /// handle.
+    ///
+    /// - Time complexity: O(1)
+    pub fn insert_item(&mut self, p: Point<f32>, value: V) -> Handle {
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.items[slot] = Some((p, value));
+                slot
+            }
+            None => {
+                self.items.push(Some((p, value)));
+                self.items.len() - 1
+            }
+        };
+        self.cells.entry(self.cell(&p)).or_default().push(slot);
+        Handle(slot)
+    }
+
+    /// Takes the slot out of the bucket of the cell containing the point `p`,
+    /// dropping the bucket once it is empty.
+    fn unlink(&mut self, p: &Point<f32>, slot: usize) {
+        let cell = self.cell(p);
+        let bucket = self.cells.get_mut(&cell).unwrap();
+        let i = bucket.iter().position(|&s| s == slot).unwrap();
+        bucket.swap_remove(i);
+        if bucket.is_empty() {
+            self.cells.remove(&cell);
+        }
+    }
+
+    /// Removes the point referred to by the handle `h` from the grid to return
+    /// it along with its value (if it was not already removed).
+    ///
+    /// - Time complexity: O(b), for b points within the same cell
+    pub fn remove(&mut self, h: Handle) -> Option<(Point<f32>, V)> {
+        let (p, value) = self.items.get_mut(h.0)?.take()?;
+        self.unlink(&p, h.0);
+        self.free.push(h.0);
+        Some((p, value))
+    }
+
+    /// Moves the point referred to by the handle `h` to `p`, which keeps its
+    /// handle and value.
+    ///
+    /// Returns `false` if the point was already removed.
+    ///
+    /// - Time complexity: O(b), for b points within the old cell
+    pub fn move_to(&mut self, h: Handle, p: Point<f32>) -> bool {
+        let old = match self.items.get(h.0) {
+            Some(Some((old, _))) => *old,
+            _ => return false,
+        };
+        if self.cell(&old) != self.cell(&p) {
+            self.unlink(&old, h.0);
+            self.cells.entry(self.cell(&p)).or_default().push(h.0);
+        }
+        if let Some((q, _)) = &mut self.items[h.0] {
+            *q = p;
+        }
+        true
+    }
+
+    /// Returns the point and value stored in the occupied `slot`.
+    fn item(&self, slot: usize) -> (&Point<f32>, &V) {
+        let (p, v) = self.items[slot].as_ref().unwrap();
+        (p, v)
+    }
+
+    /// Finds every point in the grid within a distance of `radius` from the
+    /// point `p` (including the boundary).
+    pub fn within_radius(&self, p: &Point<f32>, radius: f32) -> Vec<&Point<f32>> {
+        let mut result = Vec::new();
+        self.search_radius(p, radius, &mut |slot| result.push(self.item(slot).0));
+        result
+    }
+
+    /// Finds every point in the grid within a distance of `radius` from the
+    /// point `p` (including the boundary) along with their values.
+    pub fn within_radius_items(&self, p: &Point<f32>, radius: f32) -> Vec<(&Point<f32>, &V)> {
+        let mut result = Vec::new();
+        self.search_radius(p, radius, &mut |slot| result.push(self.item(slot)));
+        result
+    }
+
+    /// Reports the slots of the points within a distance of `radius` from the
+    /// point `p` to `report`, checking the buckets of the cells overlapping the
+    /// square around `p` (or every bucket, if there are fewer of those).
+    fn search_radius(&self, p: &Point<f32>, radius: f32, report: &mut impl FnMut(usize)) {
+        let lo = self.cell(&Point::from((p.x() - radius, p.y() - radius)));
+        let hi = self.cell(&Point::from((p.x() + radius, p.y() + radius)));
+        let mut check = |bucket: &Vec<usize>| {
+            for &slot in bucket {
+                if euclid_dist(p, self.item(slot).0) <= radius {
+                    report(slot);
+                }
+            }
+        };
+        // the cells of a huge radius saturate at the ends of the range of i64
+        let area = (hi.0.saturating_sub(lo.0).saturating_add(1))
+            .saturating_mul(hi.1.saturating_sub(lo.1).saturating_add(1));
+        if area > self.cells.len() as i64 {
+            for (cell, bucket) in &self.cells {
+                if (lo.0..=hi.0).contains(&cell.0) && (lo.1..=hi.1).contains(&cell.1) {
+                    check(bucket);
+                }
+            }
+        } else {
+            for x in lo.0..=hi.0 {
+                for y in lo.1..=hi.1 {
+                    if let Some(bucket) = self.cells.get(&(x, y)) {
+                        check(bucket);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds every pair of points in the grid within a distance of `radius`
+    /// of each other (including the boundary), listing each pair once.
+    pub fn pairs_within(&self, radius: f32) -> Vec<(&Point<f32>, &Point<f32>)> {
+        let mut result = Vec::new();
+        self.search_pairs(radius, &mut |a, b| {
+            result.push((self.item(a).0, self.item(b).0))
+        });
+        result
+    }
+
+    /// Finds every pair of points in the grid within a distance of `radius`
+    /// of each other (including the boundary) along with their handles,
+    /// listing each pair once.
+    pub fn pairs_within_handles(&self, radius: f32) -> Vec<(Handle, Handle)> {
+        let mut result = Vec::new();
+        self.search_pairs(radius, &mut |a, b| result.push((Handle(a), Handle(b))));
+        result
+    }
+
+    /// Reports the slots of every pair of points within a distance of `radius`
+    /// to `report`.
+    ///
+    /// Each bucket is compared against itself and against the cells after it
+    /// within reach of `radius` (the upper half of the surrounding cells), so
+    /// every pair of cells is only compared once. If there are fewer buckets
+    /// than cells within reach, every pair of buckets is checked instead.
+    ///
+    /// - Time complexity: O(n + k) when `radius` is close to the cell size and
+    ///   the points are evenly spread out
+    fn search_pairs(&self, radius: f32, report: &mut impl FnMut(usize, usize)) {
+        let reach = ((radius / self.cell_size).ceil() as i64).max(0);
+        let mut check = |a: usize, b: usize| {
+            if euclid_dist(self.item(a).0, self.item(b).0) <= radius {
+                report(a, b);
+            }
+        };
+        for bucket in self.cells.values() {
+            for (i, &a) in bucket.iter().enumerate() {
+                for &b in &bucket[i + 1..] {
+                    check(a, b);
+                }
+            }
+        }
+
+        let side = reach.saturating_mul(2).saturating_add(1);
+        if side.saturating_mul(side) > self.cells.len() as i64 {
+            let cells: Vec<(&Cell, &Vec<usize>)> = self.cells.iter().collect();
+            for (i, &(c, bucket)) in cells.iter().enumerate() {
+                for &(d, other) in &cells[i + 1..] {
+                    if c.0.abs_diff(d.0) <= reach as u64 && c.1.abs_diff(d.1) <= reach as u64 {
+                        for &a in bucket {
+                            for &b in other {
+                                check(a, b);
+                            }
+                        }
+                    }
+                }
+            }
+            return;
+        }
+        for (&(x, y), bucket) in &self.cells {
+            for dy in 0..=reach {
+                let dxs = match dy {
+                    0 => 1..=reach,
+                    _ => -reach..=reach,
+                };
+                for dx in dxs {
+                    if let Some(other) = self.cells.get(&(x + dx, y + dy)) {
+                        for &a in bucket {
+                            for &b in other {
+                                check(a, b);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Sorts the points by increasing x-coordinate, and then increasing y-coordinate.
+    fn sorted(mut points: Vec<&Point<f32>>) -> Vec<&Point<f32>> {
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        points
+    }
+
+    #[test]
+    fn ut_radius_query_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut grid = UniformGrid::new(5.0);
+        let mut live: Vec<(Handle, Point<f32>)> = Vec::new();
+        for _ in 0..2_000 {
+            match rng.gen_range(0..4) {
+                0 if !live.is_empty() => {
+                    let (h, p) = live.swap_remove(rng.gen_range(0..live.len()));
+                    assert_eq!(grid.remove(h), Some((p, ())));
+                    assert_eq!(grid.remove(h), None);
+                }
+                1 if !live.is_empty() => {
+                    let i = rng.gen_range(0..live.len());
+                    let p = sample_point(&mut rng, -50.0..50.0);
+                    assert!(grid.move_to(live[i].0, p));
+                    live[i].1 = p;
+                }
+                _ => {
+                    let p = sample_point(&mut rng, -50.0..50.0);
+                    live.push((grid.insert(p), p));
+                }
+            }
+        }
+        assert_eq!(grid.len(), live.len());
+        for (h, p) in &live {
+            assert_eq!(grid.get(*h), Some((p, &())));
+        }
+
+        // radii smaller and larger than the cells, and larger than the points' spread
+        for radius in [0.0, 2.0, 12.0, 200.0, 1e30, f32::INFINITY] {
+            for _ in 0..20 {
+                let p = sample_point(&mut rng, -50.0..50.0);
+                let expected = live
+                    .iter()
+                    .map(|(_, q)| q)
+                    .filter(|q| euclid_dist(&p, q) <= radius)
+                    .collect();
+                assert_eq!(sorted(grid.within_radius(&p, radius)), sorted(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn ut_pairs_within_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let points: Vec<Point<f32>> = (0..400)
+            .map(|_| sample_point(&mut rng, -50.0..50.0))
+            .collect();
+        let grid = UniformGrid::construct(points.clone(), 4.0);
+        // the larger radii reach more cells than there are buckets
+        for radius in [0.0, 3.0, 4.0, 9.5, 40.0, 1e30, f32::INFINITY] {
+            let mut expected = Vec::new();
+            for i in 0..points.len() {
+                for j in i + 1..points.len() {
+                    if euclid_dist(&points[i], &points[j]) <= radius {
+                        expected.push((i, j));
+                    }
+                }
+            }
+            let mut output: Vec<(usize, usize)> = grid
+                .pairs_within_handles(radius)
+                .into_iter()
+                .map(|(a, b)| (a.0.min(b.0), a.0.max(b.0)))
+                .collect();
+            output.sort();
+            assert_eq!(output, expected);
+            assert_eq!(grid.pairs_within(radius).len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn ut_duplicates() {
+        let p = Point::from((1.0, 1.0));
+        let grid = UniformGrid::construct(vec![p, p, p, Point::from((-1.0, -1.0))], 1.0);
+        assert_eq!(grid.within_radius(&p, 0.0).len(), 3);
+        assert_eq!(grid.pairs_within(0.0).len(), 3);
+        assert_eq!(grid.pairs_within(3.0).len(), 6);
+    }
+}
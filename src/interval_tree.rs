@@ -0,0 +1,531 @@
+/// Project: euclid
+/// Module: interval_tree
+///
+/// This file contains functions to construct, update, and query an interval
+/// tree data structure over intervals on a line.
+use crate::primitives::*;
+use std::cmp::Ordering;
+
+/// Index of a node within the tree's arena of nodes.
+type Child = Option<usize>;
+
+/// A closed interval `[low, high]` of scalars `T`, such as a time range or the
+/// extent of a shape along an axis.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Interval<T> {
+    low: T,
+    high: T,
+}
+
+impl<T: PartialOrd + Copy> Interval<T> {
+    /// Creates the interval between the endpoints `a` and `b` (in either
+    /// order).
+    pub fn new(a: T, b: T) -> Self {
+        match b < a {
+            true => Self { low: b, high: a },
+            false => Self { low: a, high: b },
+        }
+    }
+
+    /// Returns the lowest endpoint of the interval.
+    pub fn low(&self) -> T {
+        self.low
+    }
+
+    /// Returns the highest endpoint of the interval.
+    pub fn high(&self) -> T {
+        self.high
+    }
+
+    /// Checks if the value `q` is within the interval (including the
+    /// endpoints).
+    pub fn contains(&self, q: T) -> bool {
+        self.low <= q && q <= self.high
+    }
+
+    /// Checks if the interval shares at least one value with `other`.
+    pub fn overlaps(&self, other: &Interval<T>) -> bool {
+        self.low <= other.high && other.low <= self.high
+    }
+
+    /// Compares two intervals by their lowest endpoint, and then by their
+    /// highest endpoint (if equal).
+    fn compare(&self, other: &Interval<T>) -> Ordering {
+        match self.low.partial_cmp(&other.low).unwrap() {
+            Ordering::Equal => self.high.partial_cmp(&other.high).unwrap(),
+            ordering => ordering,
+        }
+    }
+}
+
+impl From<&LineSegment> for Interval<Coordinate> {
+    /// Creates the extent of the line segment along the x-axis.
+    fn from(s: &LineSegment) -> Self {
+        Self::new(s.x_min(), s.x_max())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Node<T, V> {
+    interval: Interval<T>,
+    // the value associated with the interval (taken once removed)
+    value: Option<V>,
+    // the highest endpoint among the intervals within the subtree
+    max: T,
+    // the number of intervals within the subtree
+    size: usize,
+    left: Child,
+    right: Child,
+}
+
+impl<T: Copy, V> Node<T, V> {
+    pub fn new(interval: Interval<T>, value: V) -> Self {
+        Self {
+            interval,
+            value: Some(value),
+            max: interval.high,
+            size: 1,
+            left: None,
+            right: None,
+        }
+    }
+}
+
+/// The largest fraction of a subtree's intervals that may be stored in one of
+/// its children before the subtree is rebuilt (scapegoat balance factor).
+const ALPHA: f32 = 0.7;
+
+/// An interval tree over closed intervals of scalars `T`.
+///
+/// The intervals are kept in a binary search tree ordered by their lowest
+/// endpoint, where every node also stores the highest endpoint found within
+/// its subtree, so whole subtrees that end before a query can be skipped.
+///
+/// The nodes are stored in a single flat arena and linked by index. The tree
+/// stays balanced under updates in the manner of a scapegoat tree: an
+/// insertion that lands too deep rebuilds the lowest unbalanced subtree on
+/// its path, and the whole tree is rebuilt once enough intervals are removed.
+#[derive(Debug, PartialEq)]
+pub struct IntervalTree<T, V = ()> {
+    nodes: Vec<Node<T, V>>,
+    root: Child,
+    // vacant slots in the arena left behind by removed nodes
+    free: Vec<usize>,
+    // the number of intervals since the last rebuild of the whole tree
+    max_len: usize,
+}
+
+impl<T: PartialOrd + Copy> IntervalTree<T> {
+    /// Builds an interval tree for the set of known `intervals`.
+    pub fn construct(intervals: Vec<Interval<T>>) -> Self {
+        Self::construct_items(intervals.into_iter().map(|i| (i, ())).collect())
+    }
+
+    /// Adds the interval `i` to the tree.
+    pub fn insert(&mut self, i: Interval<T>) {
+        self.insert_item(i, ())
+    }
+}
+
+impl<T: PartialOrd + Copy, V> IntervalTree<T, V> {
+    /// Builds an interval tree for the set of known intervals holding values
+    /// `items`.
+    ///
+    /// - Time complexity: O(n log(n))
+    pub fn construct_items(mut items: Vec<(Interval<T>, V)>) -> Self {
+        items.sort_by(|a, b| a.0.compare(&b.0));
+        let nodes: Vec<Node<T, V>> = items.into_iter().map(|(i, v)| Node::new(i, v)).collect();
+        let ids: Vec<usize> = (0..nodes.len()).collect();
+        let mut tree = Self {
+            max_len: nodes.len(),
+            nodes,
+            root: None,
+            free: Vec::new(),
+        };
+        tree.root = tree.link(&ids);
+        tree
+    }
+
+    /// Returns the number of intervals stored in the tree.
+    pub fn len(&self) -> usize {
+        self.root.map_or(0, |r| self.nodes[r].size)
+    }
+
+    /// Checks if the tree stores no intervals.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Recomputes the size and highest endpoint of the node at `id` from its
+    /// children.
+    fn refresh(&mut self, id: usize) {
+        let node = &self.nodes[id];
+        let (mut max, mut size) = (node.interval.high, 1);
+        for child in [node.left, node.right].into_iter().flatten() {
+            let c = &self.nodes[child];
+            if c.max > max {
+                max = c.max;
+            }
+            size += c.size;
+        }
+        let node = &mut self.nodes[id];
+        node.max = max;
+        node.size = size;
+    }
+
+    /// Recursive function call to link the nodes at the indices `ids` (sorted
+    /// by interval) into a balanced subtree.
+    fn link(&mut self, ids: &[usize]) -> Child {
+        if ids.is_empty() {
+            return None;
+        }
+        let middle = ids.len() / 2;
+        let id = ids[middle];
+        self.nodes[id].left = self.link(&ids[..middle]);
+        self.nodes[id].right = self.link(&ids[middle + 1..]);
+        self.refresh(id);
+        Some(id)
+    }
+
+    /// Stores `node` in a vacant slot of the arena (or at its end).
+    fn alloc(&mut self, node: Node<T, V>) -> usize {
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = node;
+                id
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Points the link that leads to the node at the end of `path` (the root
+    /// when there is no parent) towards `child` instead.
+    fn relink(&mut self, path: &[usize], child: Child) {
+        match path.len() {
+            0 | 1 => self.root = child,
+            n => {
+                let (parent, old) = (path[n - 2], path[n - 1]);
+                match self.nodes[parent].left == Some(old) {
+                    true => self.nodes[parent].left = child,
+                    false => self.nodes[parent].right = child,
+                }
+            }
+        }
+    }
+
+    /// Collects the indices of the nodes stored below `node` in sorted order.
+    fn collect_subtree(&self, node: usize) -> Vec<usize> {
+        let mut ids = Vec::new();
+        let mut stack = Vec::new();
+        let mut cur = Some(node);
+        while cur.is_some() || !stack.is_empty() {
+            while let Some(id) = cur {
+                stack.push(id);
+                cur = self.nodes[id].left;
+            }
+            let id = stack.pop().unwrap();
+            ids.push(id);
+            cur = self.nodes[id].right;
+        }
+        ids
+    }
+
+    /// Rebuilds the subtree at the end of `path` into a balanced subtree.
+    fn rebuild_subtree(&mut self, path: &[usize]) {
+        let ids = self.collect_subtree(path[path.len() - 1]);
+        let id = self.link(&ids);
+        self.relink(path, id);
+    }
+
+    /// Adds the interval `i` holding `value` to the tree.
+    ///
+    /// When the new node ends up deeper than log_(1/α)(n), the lowest ancestor
+    /// with a child holding more than α of its intervals is rebuilt.
+    ///
+    /// - Time complexity: O(log(n)) amortized
+    pub fn insert_item(&mut self, i: Interval<T>, value: V) {
+        let id = self.alloc(Node::new(i, value));
+        let Some(root) = self.root else {
+            self.root = Some(id);
+            self.max_len = self.max_len.max(1);
+            return;
+        };
+
+        // follow the search tree down to an empty link
+        let mut path = vec![root];
+        let mut cur = root;
+        loop {
+            let node = &mut self.nodes[cur];
+            let next = match i.compare(&node.interval) {
+                Ordering::Less => &mut node.left,
+                _ => &mut node.right,
+            };
+            match *next {
+                Some(child) => cur = child,
+                None => {
+                    *next = Some(id);
+                    break;
+                }
+            }
+            path.push(cur);
+        }
+        path.push(id);
+        for &id in path.iter().rev().skip(1) {
+            self.refresh(id);
+        }
+        self.max_len = self.max_len.max(self.len());
+
+        let depth = (path.len() - 1) as f32;
+        if depth > (self.len() as f32).ln() / (1.0 / ALPHA).ln() {
+            // the scapegoat is the lowest ancestor with an oversized child
+            for k in (0..path.len() - 1).rev() {
+                let (node, child) = (&self.nodes[path[k]], &self.nodes[path[k + 1]]);
+                if child.size as f32 > ALPHA * node.size as f32 {
+                    self.rebuild_subtree(&path[..=k]);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Removes one copy of the interval `i` from the tree.
+    ///
+    /// Returns the value held by the removed copy, or `None` if the interval
+    /// `i` was not found in the tree.
+    ///
+    /// - Time complexity: O(log(n)) amortized
+    pub fn remove(&mut self, i: &Interval<T>) -> Option<V> {
+        // follow the search tree down to a copy of the interval
+        let mut path = Vec::new();
+        let mut cur = self.root;
+        while let Some(id) = cur {
+            path.push(id);
+            let node = &self.nodes[id];
+            cur = match i.compare(&node.interval) {
+                Ordering::Less => node.left,
+                Ordering::Greater => node.right,
+                Ordering::Equal => break,
+            };
+        }
+        let &z = path.last()?;
+        if self.nodes[z].interval.compare(i) != Ordering::Equal {
+            return None;
+        }
+
+        let value = match (self.nodes[z].left, self.nodes[z].right) {
+            // replace the interval with its successor, which has no left child
+            (Some(_), Some(right)) => {
+                let mut cur = Some(right);
+                while let Some(id) = cur {
+                    path.push(id);
+                    cur = self.nodes[id].left;
+                }
+                let y = path[path.len() - 1];
+                let successor = self.nodes[y].value.take();
+                self.nodes[z].interval = self.nodes[y].interval;
+                std::mem::replace(&mut self.nodes[z].value, successor)
+            }
+            _ => self.nodes[z].value.take(),
+        };
+
+        // splice out the node at the end of the path, which has at most one child
+        let y = path[path.len() - 1];
+        let child = self.nodes[y].left.or(self.nodes[y].right);
+        self.relink(&path, child);
+        self.free.push(y);
+        for &id in path[..path.len() - 1].iter().rev() {
+            self.refresh(id);
+        }
+
+        if self.is_empty() {
+            self.nodes.clear();
+            self.free.clear();
+            self.max_len = 0;
+        } else if (self.len() as f32) < ALPHA * self.max_len as f32 {
+            self.rebuild_subtree(&[self.root.unwrap()]);
+            self.max_len = self.len();
+        }
+        value
+    }
+
+    /// Finds all intervals containing the value `q`.
+    ///
+    /// - Time complexity: O(min(n, k*log(n)))
+    pub fn stabbing_query(&self, q: T) -> Vec<&Interval<T>> {
+        self.overlap_query(&Interval::new(q, q))
+    }
+
+    /// Finds all intervals containing the value `q` along with their values.
+    pub fn stabbing_query_items(&self, q: T) -> Vec<(&Interval<T>, &V)> {
+        self.overlap_query_items(&Interval::new(q, q))
+    }
+
+    /// Finds all intervals sharing at least one value with `range`.
+    ///
+    /// - Time complexity: O(min(n, k*log(n)))
+    pub fn overlap_query(&self, range: &Interval<T>) -> Vec<&Interval<T>> {
+        let mut result = Vec::new();
+        self.search(range, &mut |node| result.push(&node.interval));
+        result
+    }
+
+    /// Finds all intervals sharing at least one value with `range` along with
+    /// their values.
+    pub fn overlap_query_items(&self, range: &Interval<T>) -> Vec<(&Interval<T>, &V)> {
+        let mut result = Vec::new();
+        self.search(range, &mut |node| {
+            result.push((&node.interval, node.value.as_ref().unwrap()))
+        });
+        result
+    }
+
+    /// Reports the nodes whose intervals overlap `range` to `report`.
+    fn search<'a>(&'a self, range: &Interval<T>, report: &mut impl FnMut(&'a Node<T, V>)) {
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id];
+            // every interval within the subtree ends before the range
+            if node.max < range.low {
+                continue;
+            }
+            if node.interval.overlaps(range) {
+                report(node);
+            }
+            // the intervals of the right subtree start at or after this one
+            if node.interval.low <= range.high {
+                stack.extend(node.right);
+            }
+            stack.extend(node.left);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Returns the number of nodes on the longest path from the root to a leaf.
+    fn height<T, V>(tree: &IntervalTree<T, V>, node: Child) -> usize {
+        match node {
+            Some(id) => {
+                let n = &tree.nodes[id];
+                1 + height(tree, n.left).max(height(tree, n.right))
+            }
+            None => 0,
+        }
+    }
+
+    #[test]
+    fn ut_stabbing_query() {
+        let tree = IntervalTree::construct(vec![
+            Interval::new(1, 4),
+            Interval::new(3, 8),
+            Interval::new(6, 7),
+            Interval::new(9, 12),
+            Interval::new(10, 5),
+        ]);
+        let mut output = tree.stabbing_query(6);
+        output.sort_by(|a, b| a.compare(b));
+        assert_eq!(
+            output,
+            vec![
+                &Interval::new(3, 8),
+                &Interval::new(5, 10),
+                &Interval::new(6, 7),
+            ]
+        );
+        assert!(tree.stabbing_query(13).is_empty());
+        assert_eq!(tree.overlap_query(&Interval::new(11, 20)).len(), 1);
+    }
+
+    #[test]
+    fn ut_insert_remove_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut tree = IntervalTree::construct_items(Vec::new());
+        let mut items: Vec<(Interval<i32>, usize)> = Vec::new();
+        for id in 0..1_000 {
+            // remove a random interval every third step
+            if id % 3 == 2 {
+                let k = rng.gen_range(0..items.len());
+                let (i, _) = items[k];
+                let removed = tree.remove(&i).unwrap();
+                // a copy holding another value may have been removed instead
+                let k = items
+                    .iter()
+                    .position(|&(j, v)| j == i && v == removed)
+                    .unwrap();
+                items.swap_remove(k);
+            } else {
+                let i = Interval::new(rng.gen_range(0..100), rng.gen_range(0..100));
+                tree.insert_item(i, id);
+                items.push((i, id));
+            }
+            assert_eq!(tree.len(), items.len());
+
+            let range = Interval::new(rng.gen_range(0..100), rng.gen_range(0..100));
+            let mut expected: Vec<usize> = items
+                .iter()
+                .filter(|(i, _)| i.overlaps(&range))
+                .map(|(_, v)| *v)
+                .collect();
+            expected.sort();
+            let mut output: Vec<usize> = tree
+                .overlap_query_items(&range)
+                .into_iter()
+                .map(|(_, v)| *v)
+                .collect();
+            output.sort();
+            assert_eq!(output, expected);
+
+            let q = rng.gen_range(0..100);
+            let expected = items.iter().filter(|(i, _)| i.contains(q)).count();
+            assert_eq!(tree.stabbing_query(q).len(), expected);
+        }
+        assert_eq!(tree.remove(&Interval::new(200, 300)), None);
+    }
+
+    #[test]
+    fn ut_segment_extents() {
+        let segments = [
+            LineSegment::from((Point::from((0.0, 0.0)), Point::from((4.0, 2.0)))),
+            LineSegment::from((Point::from((6.0, 1.0)), Point::from((3.0, 5.0)))),
+            LineSegment::from((Point::from((7.0, 0.0)), Point::from((9.0, 0.0)))),
+        ];
+        let tree = IntervalTree::construct_items(
+            segments
+                .iter()
+                .enumerate()
+                .map(|(k, s)| (Interval::from(s), k))
+                .collect(),
+        );
+        let mut output: Vec<usize> = tree
+            .stabbing_query_items(3.5)
+            .into_iter()
+            .map(|(_, k)| *k)
+            .collect();
+        output.sort();
+        assert_eq!(output, vec![0, 1]);
+    }
+
+    #[test]
+    fn ut_insert_stays_balanced() {
+        let mut tree = IntervalTree::construct(Vec::new());
+        // sorted insertions degenerate into a path without rebalancing
+        for i in 0..1_000 {
+            tree.insert(Interval::new(i, i + 1));
+        }
+        let bound = 1_000_f32.ln() / (1.0 / ALPHA).ln() + 2.0;
+        assert!((height(&tree, tree.root) as f32) <= bound);
+
+        for i in 0..900 {
+            assert_eq!(tree.remove(&Interval::new(i, i + 1)), Some(()));
+        }
+        let bound = 100_f32.ln() / (1.0 / ALPHA).ln() + 2.0;
+        assert!((height(&tree, tree.root) as f32) <= bound);
+        assert_eq!(tree.len(), 100);
+    }
+}
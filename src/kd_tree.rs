@@ -3,220 +3,1554 @@
 ///
 /// This file contains functions to construct and query a kd-tree data
 /// structure.
+use crate::aggregate::*;
 use crate::primitives::*;
+use crate::query_shape::*;
 use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 
-type Child<T> = Option<Box<Node<T>>>;
+/// Index of a node within the tree's arena of nodes.
+type Child = Option<usize>;
 
 #[derive(Debug, PartialEq)]
-struct Node<T: Default + Copy> {
-    data: Point<T>,
-    left: Child<T>,
-    right: Child<T>,
+struct Node<P, V, W> {
+    data: P,
+    // the value associated with the point (only stored by leaves)
+    value: Option<V>,
+    // the axis orthogonal to the split-line (unused by leaves)
+    axis: usize,
+    left: Child,
+    right: Child,
+    // the number of points and their aggregate value within the subtree
+    count: usize,
+    agg: W,
 }
 
-impl<T: Default + Copy> Node<T> {
+impl<P, V, W> Node<P, V, W> {
     pub fn is_leaf(&self) -> bool {
         self.left.is_none() && self.right.is_none()
     }
 
-    pub fn new(data: Point<T>, left: Child<T>, right: Child<T>) -> Self {
+    pub fn new(
+        data: P,
+        value: Option<V>,
+        axis: usize,
+        left: Child,
+        right: Child,
+        count: usize,
+        agg: W,
+    ) -> Self {
         Self {
-            data: data,
-            left: left,
-            right: right,
+            data,
+            value,
+            axis,
+            left,
+            right,
+            count,
+            agg,
         }
     }
+
+    /// Returns the point stored in the leaf along with its value.
+    fn item(&self) -> (&P, &V) {
+        (&self.data, self.value.as_ref().unwrap())
+    }
+}
+
+/// A node of a kd-tree over points `P` holding values `V` with the aggregate
+/// `A`.
+type TreeNode<P, V, A> = Node<P, V, <A as Aggregate<P, V>>::Value>;
+
+/// The strategy for choosing the axis of the split-line at each level of a
+/// kd-tree.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SplitRule {
+    /// Cycle through the axes in order as the depth increases.
+    #[default]
+    Cycle,
+    /// Split along the axis where the remaining points are spread the widest.
+    MaxSpread,
+}
+
+/// A leaf found during a proximity search, ordered by the distance from its
+/// point to the query point.
+struct Candidate<'a, T> {
+    dist: f32,
+    leaf: &'a T,
+}
+
+impl<T> PartialEq for Candidate<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.dist == other.dist
+    }
 }
 
+impl<T> Eq for Candidate<'_, T> {}
+
+impl<T> PartialOrd for Candidate<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Candidate<'_, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dist.partial_cmp(&other.dist).unwrap()
+    }
+}
+
+/// A subtree waiting to be searched during a best-bin-first search, ordered so
+/// that the cell closest to the query point is popped first from a max-heap.
+struct Bin<const D: usize> {
+    // lower bound on the distance from the query point to the cell
+    bound: f32,
+    // distance from the query point to the cell along each axis
+    offsets: [f32; D],
+    node: usize,
+}
+
+impl<const D: usize> PartialEq for Bin<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bound == other.bound
+    }
+}
+
+impl<const D: usize> Eq for Bin<D> {}
+
+impl<const D: usize> PartialOrd for Bin<D> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const D: usize> Ord for Bin<D> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.bound.partial_cmp(&self.bound).unwrap()
+    }
+}
+
+/// A stable reference to a point inserted into a kd-tree, used to remove that
+/// exact point later on.
+///
+/// A handle holds the slot of its leaf in the arena along with the generation
+/// of that slot, which is bumped whenever a point is removed from it. A handle
+/// is thus invalidated once its point is removed, even after its slot is handed
+/// out again by a later insertion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle(usize, u32);
+
+/// The largest fraction of a subtree's points that may be stored in one of its
+/// children before the subtree is rebuilt (scapegoat balance factor).
+const ALPHA: f32 = 0.7;
+
+/// An iterator over the leaves of a kd-tree whose points are contained within
+/// a query shape, which walks down the tree with an explicit stack.
+struct RangeLeaves<'a, 's, P, V, W, S, const D: usize> {
+    nodes: &'a [Node<P, V, W>],
+    shape: &'s S,
+    // subtrees left to visit, along with their cell (unless entirely contained)
+    stack: Vec<(usize, Option<Bounds<D>>)>,
+}
+
+impl<'a, P: Coordinates<D>, V, W, S: QueryShape<D>, const D: usize> Iterator
+    for RangeLeaves<'a, '_, P, V, W, S, D>
+{
+    type Item = &'a Node<P, V, W>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((id, cell)) = self.stack.pop() {
+            let node = &self.nodes[id];
+            match (node.is_leaf(), cell) {
+                (true, None) => return Some(node),
+                (true, Some(_)) => {
+                    if self.shape.contains(&node.data) {
+                        return Some(node);
+                    }
+                }
+                // report every leaf of a contained subtree
+                (false, None) => {
+                    self.stack.extend(node.right.map(|c| (c, None)));
+                    self.stack.extend(node.left.map(|c| (c, None)));
+                }
+                (false, Some(cell)) => {
+                    let (lower, upper) = cell.split(node.axis, node.data.coord(node.axis));
+                    for (child, cell) in [(node.right, upper), (node.left, lower)] {
+                        if let Some(child) = child {
+                            match self.shape.classify(&cell) {
+                                Overlap::Inside => self.stack.push((child, None)),
+                                // investigate subtree further
+                                Overlap::Crossing => self.stack.push((child, Some(cell))),
+                                Overlap::Outside => (),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Returns the signed distance from the point `p` to the split-line stored in
+/// `node`.
+///
+/// A negative distance places `p` on the left (or lower) side of the line.
+fn split_offset<P: Coordinates<D>, V, W, const D: usize>(p: &P, node: &Node<P, V, W>) -> f32 {
+    p.coord(node.axis) - node.data.coord(node.axis)
+}
+
+/// A kd-tree over points in `D`-dimensional space.
+///
+/// Points are any type `P` with `D` coordinates, such as `[f32; D]`, and each
+/// point holds a value of type `V` (nothing by default) that is returned by the
+/// `_items` variants of the queries. The planar tree over `Point<f32>` is
+/// available as `KdTree2D`.
+///
+/// The nodes are stored in a single flat arena and linked by index. After
+/// construction the arena is in depth-first order, so every subtree occupies a
+/// contiguous block; insertions append to the arena and removals leave vacant
+/// slots that are reused by later nodes.
+///
+/// The tree stays balanced under updates in the manner of a scapegoat tree:
+/// an insertion that lands too deep rebuilds the lowest unbalanced subtree on
+/// its path, and the whole tree is rebuilt once enough points are removed.
+///
+/// Every node stores the number of points in its subtree along with their
+/// aggregate `A` (nothing by default), so counting and aggregate range queries
+/// use whole subtrees without visiting their leaves.
+///
+/// Ties between equal coordinates are broken by the composite-number order of
+/// `compare_from`: the left subtree of a split-point holds the points before it
+/// along the split's axis, and the right subtree holds the points after it.
+/// Only copies of the split-point itself may be stored on either side, so the
+/// cells of both subtrees keep the split-line as their boundary.
 #[derive(Debug, PartialEq)]
-pub struct KdTree {
-    root: Node<f32>,
+pub struct KdTree<const D: usize, P: Coordinates<D> = [f32; D], V = (), A: Aggregate<P, V> = ()> {
+    nodes: Vec<Node<P, V, A::Value>>,
+    root: Child,
+    rule: SplitRule,
+    // vacant slots in the arena
+    free: Vec<usize>,
+    // the number of times a point was removed from each slot (zero if missing)
+    generations: Vec<u32>,
+    // the number of points since the last rebuild of the entire tree
+    max_len: usize,
+}
+
+/// A kd-tree over points in the plane.
+pub type KdTree2D = KdTree<2, Point<f32>>;
+
+impl<const D: usize, P: Coordinates<D>> KdTree<D, P> {
+    /// Builds a kd-tree for the set of known points `p`, cycling through the
+    /// axes for the split-lines.
+    pub fn construct(points: Vec<P>) -> Self {
+        Self::construct_with(points, SplitRule::Cycle)
+    }
+
+    /// Builds a kd-tree for the set of known points `p` while choosing the
+    /// axis of each split-line according to `rule`.
+    ///
+    /// - Time complexity: O(n*log(n))
+    pub fn construct_with(points: Vec<P>, rule: SplitRule) -> Self {
+        Self::construct_aggregate(points, rule)
+    }
+}
+
+impl<const D: usize, P: Coordinates<D>, A: Aggregate<P>> KdTree<D, P, (), A> {
+    /// Builds a kd-tree for the set of known points `p` that also stores the
+    /// aggregate `A` of every subtree, while choosing the axis of each
+    /// split-line according to `rule`.
+    ///
+    /// - Time complexity: O(n*log(n))
+    pub fn construct_aggregate(points: Vec<P>, rule: SplitRule) -> Self {
+        Self::construct_aggregate_items(points.into_iter().map(|p| (p, ())).collect(), rule)
+    }
+
+    /// Adds the point `p` to the tree and returns a handle to it.
+    pub fn insert(&mut self, p: P) -> Handle {
+        self.insert_item(p, ())
+    }
+}
+
+impl<const D: usize, P: Coordinates<D>, V> KdTree<D, P, V> {
+    /// Builds a kd-tree for the set of known points holding values `items`
+    /// while choosing the axis of each split-line according to `rule`.
+    ///
+    /// - Time complexity: O(n*log(n))
+    pub fn construct_items(items: Vec<(P, V)>, rule: SplitRule) -> Self {
+        Self::construct_aggregate_items(items, rule)
+    }
 }
 
-impl KdTree {
-    /// Builds a kd-tree for the set of known points `p`.
-    pub fn construct(points: Vec<Point<f32>>) -> Self {
+impl<const D: usize, P: Coordinates<D>, V, A: Aggregate<P, V>> KdTree<D, P, V, A> {
+    /// Builds a kd-tree for the set of known points holding values `items`
+    /// that also stores the aggregate `A` of every subtree, while choosing the
+    /// axis of each split-line according to `rule`.
+    ///
+    /// - Time complexity: O(n*log(n))
+    pub fn construct_aggregate_items(items: Vec<(P, V)>, rule: SplitRule) -> Self {
+        let len = items.len();
+        // remember where each value is kept while the points are partitioned
+        let (mut points, mut values): (Vec<(P, usize)>, Vec<Option<V>>) = items
+            .into_iter()
+            .enumerate()
+            .map(|(i, (p, v))| ((p, i), Some(v)))
+            .unzip();
+        // a tree with n leaves has n - 1 internal nodes
+        let mut nodes = Vec::with_capacity(2 * len);
+        let root = match points.is_empty() {
+            true => None,
+            false => Some(Self::build(&mut nodes, &mut points, &mut values, 0, rule)),
+        };
         Self {
-            root: Self::build(points, 0),
+            nodes,
+            root,
+            rule,
+            free: Vec::new(),
+            generations: Vec::new(),
+            max_len: len,
         }
     }
 
-    /// Recursive function call to build the levels of the kd-tree while alternating
-    /// axis coordinate sorting.
+    /// Returns the number of points stored in the tree.
+    pub fn len(&self) -> usize {
+        self.root.map_or(0, |r| self.nodes[r].count)
+    }
+
+    /// Checks if the tree does not store any points.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Creates a leaf storing the point `p` holding the value `v`.
+    fn leaf(p: P, v: V) -> TreeNode<P, V, A> {
+        let agg = A::weight(&p, &v);
+        Node::new(p, Some(v), 0, None, None, 1, agg)
+    }
+
+    /// Creates a node for the split-line through `median` along `axis` above
+    /// the subtrees at `left` and `right`.
+    fn split(
+        nodes: &[TreeNode<P, V, A>],
+        median: P,
+        axis: usize,
+        left: usize,
+        right: usize,
+    ) -> TreeNode<P, V, A> {
+        let (l, r) = (&nodes[left], &nodes[right]);
+        Node::new(
+            median,
+            None,
+            axis,
+            Some(left),
+            Some(right),
+            l.count + r.count,
+            l.agg.combine(r.agg),
+        )
+    }
+
+    /// Recomputes the count and aggregate of the node at `id` from its
+    /// children.
+    fn refresh(&mut self, id: usize) {
+        let n = &self.nodes[id];
+        let node = Self::split(
+            &self.nodes,
+            n.data,
+            n.axis,
+            n.left.unwrap(),
+            n.right.unwrap(),
+        );
+        self.nodes[id] = node;
+    }
+
+    /// Recursive function call to build the levels of the kd-tree while selecting
+    /// the axis coordinate for partitioning.
     ///
-    /// At each level in the tree, the remaining subset of points are sorted
-    /// according to the current axis under comparison.
-    fn build(points: Vec<Point<f32>>, depth: usize) -> Node<f32> {
+    /// At each level in the tree, the remaining subset of points is partitioned
+    /// in place around the median along the current axis under comparison, so
+    /// no level needs to fully sort or copy its subset. Each point carries the
+    /// index of its value in `values`, which is moved into the point's leaf.
+    fn build(
+        nodes: &mut Vec<TreeNode<P, V, A>>,
+        points: &mut [(P, usize)],
+        values: &mut [Option<V>],
+        depth: usize,
+        rule: SplitRule,
+    ) -> usize {
         match points.len() {
             // create a new leaf
-            1 => Node::new(*points.get(0).unwrap(), None, None),
+            1 => {
+                let (p, i) = points[0];
+                nodes.push(Self::leaf(p, values[i].take().unwrap()));
+                nodes.len() - 1
+            }
             // recursively build sub-trees
             _ => {
-                let ordering = {
-                    match depth % 2 == 0 {
-                        // split by x-coordinate (vertical line)
-                        true => {
-                            // sort the points by increasing x-coordinate, and then increasing y-coordinate (if equal x-coordinate)
-                            let mut x_split_points = points;
-                            x_split_points.sort_by(|a, b| {
-                                match a.x().partial_cmp(&b.x()).unwrap() {
-                                    Ordering::Equal => a.y().partial_cmp(&b.y()).unwrap(),
-                                    Ordering::Greater => Ordering::Greater,
-                                    Ordering::Less => Ordering::Less,
-                                }
-                            });
-                            x_split_points
-                        }
-                        // split by y-coordinate (horizontal line)
-                        false => {
-                            // sort the points by increasing y-coordinate, and then increasing x-coordinate (if equal y-coordinate)
-                            let mut y_split_points = points;
-                            y_split_points.sort_by(|a, b| {
-                                match a.y().partial_cmp(&b.y()).unwrap() {
-                                    Ordering::Equal => a.x().partial_cmp(&b.x()).unwrap(),
-                                    Ordering::Greater => Ordering::Greater,
-                                    Ordering::Less => Ordering::Less,
-                                }
-                            });
-                            y_split_points
-                        }
-                    }
+                let axis = match rule {
+                    SplitRule::Cycle => depth % D,
+                    SplitRule::MaxSpread => Self::widest_axis(points.iter().map(|(p, _)| p)),
                 };
-                // partition by the median index
-                let median = *ordering.get(ordering.len() / 2).unwrap();
-                let (left, right) = ordering.split_at(ordering.len() / 2);
+                // select the median by increasing coordinate along the axis, and then the following axes (if equal coordinate)
+                let middle = points.len() / 2;
+                let (_, &mut (median, _), _) =
+                    points.select_nth_unstable_by(middle, |a, b| compare_from(&a.0, &b.0, axis));
 
-                let left_child = Self::build(Vec::from(left), depth + 1);
-                let right_child = Self::build(Vec::from(right), depth + 1);
+                // partition by the median index
+                let (left, right) = points.split_at_mut(middle);
 
                 // create a new node with median point to describe the split-line
-                Node::new(
+                // (reserved before its subtrees to keep each subtree contiguous)
+                let id = nodes.len();
+                nodes.push(Node::new(
                     median,
-                    Some(Box::new(left_child)),
-                    Some(Box::new(right_child)),
-                )
+                    None,
+                    axis,
+                    None,
+                    None,
+                    0,
+                    A::Value::identity(),
+                ));
+
+                let left = Self::build(nodes, left, values, depth + 1, rule);
+                let right = Self::build(nodes, right, values, depth + 1, rule);
+                nodes[id] = Self::split(nodes, median, axis, left, right);
+                id
             }
         }
     }
 
-    /// Asks a rectangular range query question for the rectangle defined by
-    /// `region`.
-    pub fn range_query(&self, region: &Region<f32>) -> Vec<&Point<f32>> {
-        self.search_tree(&region, &self.root, Vec::new(), 0)
+    /// Returns the axis with the largest difference between the lowest and
+    /// highest coordinates among `points`.
+    fn widest_axis<'a>(points: impl Iterator<Item = &'a P> + Clone) -> usize
+    where
+        P: 'a,
+    {
+        let spread = |axis: usize| {
+            let (lo, hi) = points
+                .clone()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| {
+                    (lo.min(p.coord(axis)), hi.max(p.coord(axis)))
+                });
+            hi - lo
+        };
+        (0..D)
+            .max_by(|&a, &b| spread(a).partial_cmp(&spread(b)).unwrap())
+            .unwrap_or(0)
     }
 
-    /// Recursive function call that traverses down the (sub)tree from `node`
-    /// and evaluates the parallel axis according to the current `depth` to
-    /// collect the points that are contained within the rectangular range
-    /// `region`.
-    fn search_tree<'a>(
+    /// Returns a handle to the leaf at index `leaf`.
+    fn handle(&self, leaf: usize) -> Handle {
+        Handle(leaf, self.generations.get(leaf).copied().unwrap_or(0))
+    }
+
+    /// Places `node` into a vacant slot of the arena and returns its index.
+    fn alloc(&mut self, node: TreeNode<P, V, A>) -> usize {
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = node;
+                id
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Points the link that leads to the node at the end of `path` (the root
+    /// when there is no parent) towards `child` instead.
+    fn relink(&mut self, path: &[usize], child: Child) {
+        match path.len() {
+            0 | 1 => self.root = child,
+            n => {
+                let (parent, old) = (path[n - 2], path[n - 1]);
+                match self.nodes[parent].left == Some(old) {
+                    true => self.nodes[parent].left = child,
+                    false => self.nodes[parent].right = child,
+                }
+            }
+        }
+    }
+
+    /// Collects the indices of the leaves and the internal nodes stored below
+    /// `node`.
+    fn collect_subtree(&self, node: usize) -> (Vec<usize>, Vec<usize>) {
+        let mut leaves = Vec::new();
+        let mut internal = Vec::new();
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            let n = &self.nodes[id];
+            match n.is_leaf() {
+                true => leaves.push(id),
+                false => {
+                    internal.push(id);
+                    stack.extend(n.left.iter().chain(n.right.iter()));
+                }
+            }
+        }
+        (leaves, internal)
+    }
+
+    /// Recursive function call to rebuild a balanced subtree at `depth` over
+    /// the existing `leaves`, allocating new nodes for the split-lines.
+    ///
+    /// The leaves stay in place so that handles to their points remain valid.
+    fn rebuild(&mut self, leaves: &mut [usize], depth: usize) -> usize {
+        match leaves.len() {
+            1 => leaves[0],
+            _ => {
+                let nodes = &self.nodes;
+                let axis = match self.rule {
+                    SplitRule::Cycle => depth % D,
+                    SplitRule::MaxSpread => {
+                        Self::widest_axis(leaves.iter().map(|&l| &nodes[l].data))
+                    }
+                };
+                let middle = leaves.len() / 2;
+                let (_, &mut median, _) = leaves.select_nth_unstable_by(middle, |&a, &b| {
+                    compare_from(&nodes[a].data, &nodes[b].data, axis)
+                });
+                let median = nodes[median].data;
+
+                let (left, right) = leaves.split_at_mut(middle);
+                let left = self.rebuild(left, depth + 1);
+                let right = self.rebuild(right, depth + 1);
+                let node = Self::split(&self.nodes, median, axis, left, right);
+                self.alloc(node)
+            }
+        }
+    }
+
+    /// Rebuilds the subtree at the end of `path` into a balanced subtree.
+    fn rebuild_subtree(&mut self, path: &[usize]) {
+        let (mut leaves, internal) = self.collect_subtree(path[path.len() - 1]);
+        self.free.extend(internal);
+        let id = self.rebuild(&mut leaves, path.len() - 1);
+        self.relink(path, Some(id));
+    }
+
+    /// Adds the point `p` holding `value` to the tree and returns a handle to it.
+    ///
+    /// When the new leaf ends up deeper than log_(1/α)(n), the lowest ancestor
+    /// with a child holding more than α of its points is rebuilt.
+    ///
+    /// - Time complexity: O(log(n)) amortized
+    pub fn insert_item(&mut self, p: P, value: V) -> Handle {
+        let leaf = self.alloc(Self::leaf(p, value));
+        let Some(root) = self.root else {
+            self.root = Some(leaf);
+            self.max_len = self.max_len.max(1);
+            return self.handle(leaf);
+        };
+
+        // follow the split-lines down to the leaf whose cell contains p
+        let mut path = vec![root];
+        let mut id = root;
+        while !self.nodes[id].is_leaf() {
+            let node = &self.nodes[id];
+            id = match compare_from(&p, &node.data, node.axis) {
+                Ordering::Less => node.left.unwrap(),
+                _ => node.right.unwrap(),
+            };
+            path.push(id);
+        }
+        // split the cell between the existing point and p
+        let split = self.rebuild(&mut [id, leaf], path.len() - 1);
+        self.relink(&path, Some(split));
+        *path.last_mut().unwrap() = split;
+        for &id in path.iter().rev().skip(1) {
+            self.refresh(id);
+        }
+        self.max_len = self.max_len.max(self.len());
+
+        let depth = path.len() as f32;
+        if depth > (self.len() as f32).ln() / (1.0 / ALPHA).ln() {
+            // the scapegoat is the lowest ancestor with an oversized child
+            for i in (0..path.len() - 1).rev() {
+                let (node, child) = (&self.nodes[path[i]], &self.nodes[path[i + 1]]);
+                if child.count as f32 > ALPHA * node.count as f32 {
+                    self.rebuild_subtree(&path[..=i]);
+                    break;
+                }
+            }
+        }
+        self.handle(leaf)
+    }
+
+    /// Removes one copy of the point `p` from the tree.
+    ///
+    /// Returns the value held by the removed copy, or `None` if the point `p`
+    /// was not found in the tree.
+    ///
+    /// - Time complexity: O(log(n)) amortized, for distinct points
+    pub fn remove(&mut self, p: &P) -> Option<V> {
+        let mut path = Vec::new();
+        match self.find_leaf(p, None, self.root, &mut path) {
+            true => {
+                let value = self.nodes[path[path.len() - 1]].value.take();
+                self.detach(&path);
+                value
+            }
+            false => None,
+        }
+    }
+
+    /// Removes the point referred to by the handle `h` from the tree.
+    ///
+    /// Returns the removed point and its value, or `None` if the handle is no
+    /// longer valid.
+    pub fn remove_handle(&mut self, h: Handle) -> Option<(P, V)> {
+        if self.handle(h.0) != h {
+            return None;
+        }
+        let p = self.nodes.get(h.0)?.data;
+        let mut path = Vec::new();
+        match self.find_leaf(&p, Some(h.0), self.root, &mut path) {
+            true => {
+                let value = self.nodes[h.0].value.take().unwrap();
+                self.detach(&path);
+                Some((p, value))
+            }
+            false => None,
+        }
+    }
+
+    /// Recursive function call to record the `path` from `node` down to a leaf
+    /// storing the point `p` (or the leaf at index `target`, when given).
+    ///
+    /// Points equal to a split-point may be on either side of its split-line,
+    /// so both sides are searched in that case.
+    fn find_leaf(&self, p: &P, target: Option<usize>, node: Child, path: &mut Vec<usize>) -> bool {
+        let Some(id) = node else {
+            return false;
+        };
+        path.push(id);
+        let n = &self.nodes[id];
+        let found = match n.is_leaf() {
+            true => match target {
+                Some(t) => t == id,
+                None => compare_from(p, &n.data, 0) == Ordering::Equal,
+            },
+            false => match compare_from(p, &n.data, n.axis) {
+                Ordering::Less => self.find_leaf(p, target, n.left, path),
+                Ordering::Greater => self.find_leaf(p, target, n.right, path),
+                Ordering::Equal => {
+                    self.find_leaf(p, target, n.right, path)
+                        || self.find_leaf(p, target, n.left, path)
+                }
+            },
+        };
+        if !found {
+            path.pop();
+        }
+        found
+    }
+
+    /// Removes the leaf at the end of `path` by replacing its parent with its
+    /// sibling, and rebuilds the entire tree once it has shrunk below α of its
+    /// size since the last rebuild.
+    fn detach(&mut self, path: &[usize]) {
+        let leaf = path[path.len() - 1];
+        self.free.push(leaf);
+        if self.generations.len() <= leaf {
+            self.generations.resize(leaf + 1, 0);
+        }
+        self.generations[leaf] = self.generations[leaf].wrapping_add(1);
+        match path.len() {
+            1 => self.root = None,
+            n => {
+                let parent = &self.nodes[path[n - 2]];
+                let sibling = match parent.left == Some(leaf) {
+                    true => parent.right,
+                    false => parent.left,
+                };
+                self.free.push(path[n - 2]);
+                self.relink(&path[..n - 1], sibling);
+                for &id in path[..n - 2].iter().rev() {
+                    self.refresh(id);
+                }
+            }
+        }
+
+        if self.is_empty() {
+            self.nodes.clear();
+            self.free.clear();
+            self.max_len = 0;
+        } else if (self.len() as f32) < ALPHA * self.max_len as f32 {
+            self.rebuild_subtree(&[self.root.unwrap()]);
+            self.max_len = self.len();
+        }
+    }
+
+    /// Asks a range query question for the points contained within `shape`
+    /// (such as a `Region` or a `Bounds` box, a `Circle`, a `HalfPlane`, or a
+    /// `ConvexPolygon`).
+    pub fn range_query<S: QueryShape<D>>(&self, shape: &S) -> Vec<&P> {
+        self.range_leaves(shape).map(|n| &n.data).collect()
+    }
+
+    /// Asks a range query question for the points contained within `shape`
+    /// to return the points along with their values.
+    pub fn range_query_items<S: QueryShape<D>>(&self, shape: &S) -> Vec<(&P, &V)> {
+        self.range_leaves(shape).map(|n| n.item()).collect()
+    }
+
+    /// Iterates over the points contained within `shape` while lazily walking
+    /// down the tree, so the caller can stop early.
+    pub fn range_iter<'a, S: QueryShape<D>>(
         &'a self,
-        region: &Region<f32>,
-        node: &'a Node<f32>,
-        mut result: Vec<&'a Point<f32>>,
-        depth: usize,
-    ) -> Vec<&Point<f32>> {
+        shape: &'a S,
+    ) -> impl Iterator<Item = &'a P> + 'a {
+        self.range_leaves(shape).map(|n| &n.data)
+    }
+
+    /// Iterates over the points contained within `shape` along with their
+    /// values while lazily walking down the tree.
+    pub fn range_iter_items<'a, S: QueryShape<D>>(
+        &'a self,
+        shape: &'a S,
+    ) -> impl Iterator<Item = (&'a P, &'a V)> + 'a {
+        self.range_leaves(shape).map(|n| n.item())
+    }
+
+    /// Starts the walk over the leaves whose points are contained within
+    /// `shape`.
+    fn range_leaves<'s, S: QueryShape<D>>(
+        &self,
+        shape: &'s S,
+    ) -> RangeLeaves<'_, 's, P, V, A::Value, S, D> {
+        RangeLeaves {
+            nodes: &self.nodes,
+            shape,
+            stack: self
+                .root
+                .iter()
+                .map(|&r| (r, Some(Bounds::everything())))
+                .collect(),
+        }
+    }
+
+    /// Counts the points contained within `shape`.
+    ///
+    /// - Time complexity: O(n^(1-1/d)) for d dimensions and a box
+    pub fn range_count<S: QueryShape<D>>(&self, shape: &S) -> usize {
+        let mut count = 0;
+        self.search_region(shape, &mut |node| count += node.count);
+        count
+    }
+
+    /// Combines the aggregate values of the points contained within `shape`.
+    ///
+    /// - Time complexity: O(n^(1-1/d)) for d dimensions and a box
+    pub fn range_aggregate<S: QueryShape<D>>(&self, shape: &S) -> A::Value {
+        let mut agg = A::Value::identity();
+        self.search_region(shape, &mut |node| agg = agg.combine(node.agg));
+        agg
+    }
+
+    /// Reports the canonical subtrees for the range `shape` to `report`.
+    fn search_region<'a, S: QueryShape<D>>(
+        &'a self,
+        shape: &S,
+        report: &mut impl FnMut(&'a TreeNode<P, V, A>),
+    ) {
+        if let Some(root) = self.root {
+            self.search_tree(shape, &self.nodes[root], Bounds::everything(), report);
+        }
+    }
+
+    /// Recursive function call that traverses down the (sub)tree from `node`,
+    /// whose points lie within the box `cell`, to report the largest subtrees
+    /// whose points are all contained within the range `shape`.
+    fn search_tree<'a, S: QueryShape<D>>(
+        &'a self,
+        shape: &S,
+        node: &'a TreeNode<P, V, A>,
+        cell: Bounds<D>,
+        report: &mut impl FnMut(&'a TreeNode<P, V, A>),
+    ) {
         match node.is_leaf() {
             // report the point that is stored in v
             true => {
-                if region.contains_point(&node.data) == true {
-                    result.push(&node.data);
+                if shape.contains(&node.data) {
+                    report(node);
                 }
             }
             false => {
-                match depth % 2 == 0 {
-                    // split by vertical axis (x)
-                    true => {
-                        if let Some(lc) = &node.left {
-                            if let Some(left_region) =
-                                &region.intersect_left_halfplane(node.data.x())
-                            {
-                                if region.strictly_contains(left_region) == true {
-                                    result = self.report_subtree(lc, result);
-                                }
-                                // investigate subtree further
-                                else if region.intersects(left_region) == true {
-                                    result = self.search_tree(region, lc, result, depth + 1)
-                                }
-                            }
-                        }
-                        // decide if to traverse the right child (if it exists)
-                        if let Some(rc) = &node.right {
-                            if let Some(right_region) =
-                                &region.intersect_right_halfplane(node.data.x())
-                            {
-                                if region.strictly_contains(right_region) == true {
-                                    result = self.report_subtree(rc, result);
-                                }
-                                // // investigate subtree further
-                                else if region.intersects(right_region) == true {
-                                    result = self.search_tree(region, rc, result, depth + 1)
-                                }
-                            }
+                let (lower, upper) = cell.split(node.axis, node.data.coord(node.axis));
+                for (child, cell) in [(node.left, lower), (node.right, upper)] {
+                    if let Some(child) = child {
+                        let child = &self.nodes[child];
+                        match shape.classify(&cell) {
+                            Overlap::Inside => report(child),
+                            // investigate subtree further
+                            Overlap::Crossing => self.search_tree(shape, child, cell, report),
+                            Overlap::Outside => (),
                         }
                     }
-                    // split by horizontal axis (y)
-                    false => {
-                        // decide if to traverse the left child (if it exists)
-                        if let Some(lc) = &node.left {
-                            if let Some(lower_region) =
-                                &region.intersect_lower_halfplane(node.data.y())
-                            {
-                                if region.strictly_contains(lower_region) == true {
-                                    result = self.report_subtree(lc, result);
-                                }
-                                // investigate subtree further
-                                else if region.intersects(lower_region) == true {
-                                    result = self.search_tree(region, lc, result, depth + 1)
-                                }
+                }
+            }
+        }
+    }
+
+    /// Finds the closest point in the tree to the point `p`.
+    pub fn nearest(&self, p: &P) -> Option<&P> {
+        self.k_nearest(p, 1).into_iter().next()
+    }
+
+    /// Finds the closest point in the tree to the point `p` along with its
+    /// value.
+    pub fn nearest_item(&self, p: &P) -> Option<(&P, &V)> {
+        self.k_nearest_items(p, 1).into_iter().next()
+    }
+
+    /// Finds the `k` closest points in the tree to the point `p`, sorted by
+    /// increasing distance.
+    ///
+    /// The best candidates found so far are kept in a max-heap bounded by `k`,
+    /// so a subtree is skipped once its split-line is farther away than the
+    /// worst candidate.
+    pub fn k_nearest(&self, p: &P, k: usize) -> Vec<&P> {
+        self.k_nearest_leaves(p, k).map(|n| &n.data).collect()
+    }
+
+    /// Finds the `k` closest points in the tree to the point `p` along with
+    /// their values, sorted by increasing distance.
+    pub fn k_nearest_items(&self, p: &P, k: usize) -> Vec<(&P, &V)> {
+        self.k_nearest_leaves(p, k).map(|n| n.item()).collect()
+    }
+
+    /// Collects the leaves of the `k` closest points to `p`, sorted by
+    /// increasing distance.
+    fn k_nearest_leaves(&self, p: &P, k: usize) -> impl Iterator<Item = &TreeNode<P, V, A>> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if let (true, Some(root)) = (k > 0, self.root) {
+            self.search_nearest(p, k, &self.nodes[root], &mut heap);
+        }
+        heap.into_sorted_vec().into_iter().map(|c| c.leaf)
+    }
+
+    /// Recursive function call that traverses down the (sub)tree from `node`
+    /// to collect the `k` closest points to `p` within `heap`.
+    fn search_nearest<'a>(
+        &'a self,
+        p: &P,
+        k: usize,
+        node: &'a TreeNode<P, V, A>,
+        heap: &mut BinaryHeap<Candidate<'a, TreeNode<P, V, A>>>,
+    ) {
+        if node.is_leaf() {
+            heap.push(Candidate {
+                dist: p.dist(&node.data),
+                leaf: node,
+            });
+            if heap.len() > k {
+                heap.pop();
+            }
+            return;
+        }
+        let offset = split_offset(p, node);
+        // visit the side of the split-line containing p first
+        let (near, far) = match offset < 0.0 {
+            true => (node.left, node.right),
+            false => (node.right, node.left),
+        };
+        if let Some(near) = near {
+            self.search_nearest(p, k, &self.nodes[near], heap);
+        }
+        if let Some(far) = far {
+            let worst = match heap.len() < k {
+                true => f32::INFINITY,
+                false => heap.peek().unwrap().dist,
+            };
+            if offset.abs() < worst {
+                self.search_nearest(p, k, &self.nodes[far], heap);
+            }
+        }
+    }
+
+    /// Finds every point in the tree within a distance of `radius` from the
+    /// point `p` (including the boundary).
+    pub fn within_radius(&self, p: &P, radius: f32) -> Vec<&P> {
+        self.within_radius_leaves(p, radius)
+            .into_iter()
+            .map(|n| &n.data)
+            .collect()
+    }
+
+    /// Finds every point in the tree within a distance of `radius` from the
+    /// point `p` (including the boundary) along with their values.
+    pub fn within_radius_items(&self, p: &P, radius: f32) -> Vec<(&P, &V)> {
+        self.within_radius_leaves(p, radius)
+            .into_iter()
+            .map(|n| n.item())
+            .collect()
+    }
+
+    /// Collects the leaves whose points are within a distance of `radius`
+    /// from the point `p`.
+    fn within_radius_leaves(&self, p: &P, radius: f32) -> Vec<&TreeNode<P, V, A>> {
+        let mut result = Vec::new();
+        let mut stack: Vec<&TreeNode<P, V, A>> =
+            self.root.iter().map(|&r| &self.nodes[r]).collect();
+        while let Some(node) = stack.pop() {
+            if node.is_leaf() {
+                if p.dist(&node.data) <= radius {
+                    result.push(node);
+                }
+                continue;
+            }
+            let offset = split_offset(p, node);
+            // skip a side of the split-line that is farther away than the radius
+            if let Some(left) = node.left {
+                if offset <= radius {
+                    stack.push(&self.nodes[left]);
+                }
+            }
+            if let Some(right) = node.right {
+                if -offset <= radius {
+                    stack.push(&self.nodes[right]);
+                }
+            }
+        }
+        result
+    }
+
+    /// Finds a point in the tree whose distance to the point `p` is within a
+    /// factor of (1 + `eps`) of the distance to the true nearest point.
+    ///
+    /// Returns the point along with the number of nodes visited during the
+    /// search. Setting `eps` to 0 finds the exact nearest point.
+    pub fn approx_nearest(&self, p: &P, eps: f32) -> (Option<&P>, usize) {
+        self.search_best_bin(p, eps, usize::MAX)
+    }
+
+    /// Finds a point in the tree close to the point `p` by visiting the cells
+    /// in order of their distance to `p`, until `budget` nodes are visited.
+    ///
+    /// Returns the closest point found along with the number of nodes visited
+    /// during the search. The search only stops between descents, so the
+    /// number of nodes visited can exceed `budget` by the height of the tree.
+    pub fn best_bin_first(&self, p: &P, budget: usize) -> (Option<&P>, usize) {
+        self.search_best_bin(p, 0.0, budget)
+    }
+
+    /// Repeatedly descends from the cell closest to `p` down to a leaf while
+    /// queueing the cells on the far side of each split-line.
+    ///
+    /// The search ends once the closest queued cell cannot improve the best
+    /// point by more than a factor of (1 + `eps`), or when `budget` nodes have
+    /// been visited.
+    fn search_best_bin(&self, p: &P, eps: f32, budget: usize) -> (Option<&P>, usize) {
+        let mut best: Option<Candidate<TreeNode<P, V, A>>> = None;
+        let mut visited = 0;
+        let mut queue = BinaryHeap::new();
+        if let Some(root) = self.root {
+            queue.push(Bin {
+                bound: 0.0,
+                offsets: [0.0; D],
+                node: root,
+            });
+        }
+        while let Some(bin) = queue.pop() {
+            if let Some(b) = &best {
+                if bin.bound * (1.0 + eps) >= b.dist {
+                    break;
+                }
+            }
+            if visited >= budget {
+                break;
+            }
+            // descend to the leaf on the same side of every split-line as p
+            let mut node = &self.nodes[bin.node];
+            while !node.is_leaf() {
+                visited += 1;
+                let offset = split_offset(p, node);
+                let (near, far) = match offset < 0.0 {
+                    true => (node.left, node.right),
+                    false => (node.right, node.left),
+                };
+                if let Some(far) = far {
+                    let mut offsets = bin.offsets;
+                    offsets[node.axis] = offset.abs();
+                    queue.push(Bin {
+                        bound: f32::sqrt(offsets.iter().map(|o| o * o).sum()),
+                        offsets,
+                        node: far,
+                    });
+                }
+                match near {
+                    Some(near) => node = &self.nodes[near],
+                    None => break,
+                }
+            }
+            visited += 1;
+            let dist = p.dist(&node.data);
+            if best.as_ref().is_none_or(|b| dist < b.dist) {
+                best = Some(Candidate { dist, leaf: node });
+            }
+        }
+        (best.map(|b| &b.leaf.data), visited)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Generate randomly sampled points, optionally snapped to a coarse grid.
+    fn sample_points(rng: &mut impl Rng, size: usize, grid: bool) -> Vec<Point<f32>> {
+        (0..size)
+            .map(|_| match grid {
+                true => Point::from((rng.gen_range(0..16) as f32, rng.gen_range(0..16) as f32)),
+                false => sample_point(rng, 0.0..100.0),
+            })
+            .collect()
+    }
+
+    /// Sorts the distances from `p` to each point in `points`.
+    fn distances(p: &Point<f32>, points: &[&Point<f32>]) -> Vec<f32> {
+        let mut dists: Vec<f32> = points.iter().map(|q| euclid_dist(p, q)).collect();
+        dists.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        dists
+    }
+
+    #[test]
+    fn ut_nearest() {
+        let input = vec![
+            Point::from((0.0, 0.0)),
+            Point::from((2.0, 10.0)),
+            Point::from((5.0, 1.0)),
+            Point::from((2.0, 4.0)),
+            Point::from((3.0, 2.0)),
+        ];
+        let tree = KdTree::construct(input);
+
+        assert_eq!(
+            tree.nearest(&Point::from((4.0, 1.0))),
+            Some(&Point::from((5.0, 1.0)))
+        );
+        assert_eq!(
+            tree.k_nearest(&Point::from((2.0, 3.5)), 3),
+            vec![
+                &Point::from((2.0, 4.0)),
+                &Point::from((3.0, 2.0)),
+                &Point::from((5.0, 1.0)),
+            ]
+        );
+        assert_eq!(
+            tree.k_nearest(&Point::from((2.0, 3.0)), 0),
+            Vec::<&Point<f32>>::new()
+        );
+        assert_eq!(tree.k_nearest(&Point::from((2.0, 3.0)), 9).len(), 5);
+        assert_eq!(tree.within_radius(&Point::from((0.0, 0.0)), 5.0).len(), 3);
+    }
+
+    #[test]
+    fn ut_nearest_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for grid in [false, true] {
+            let points = sample_points(&mut rng, 500, grid);
+            let everything: Vec<&Point<f32>> = points.iter().collect();
+            let tree = KdTree::construct(points.clone());
+            for _ in 0..50 {
+                let q = sample_point(&mut rng, 0.0..100.0);
+                let expected = distances(&q, &everything);
+
+                let nearest = tree.nearest(&q).unwrap();
+                assert_eq!(euclid_dist(&q, nearest), expected[0]);
+
+                let k = rng.gen_range(1..20);
+                assert_eq!(distances(&q, &tree.k_nearest(&q, k)), expected[..k]);
+
+                let radius = rng.gen::<f32>() * 20.0;
+                let within = tree.within_radius(&q, radius);
+                let count = expected.iter().filter(|&&d| d <= radius).count();
+                assert_eq!(within.len(), count);
+                assert!(within.iter().all(|p| euclid_dist(&q, p) <= radius));
+            }
+        }
+    }
+
+    #[test]
+    fn ut_approx_nearest() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let points = sample_points(&mut rng, 2_000, false);
+        let everything: Vec<&Point<f32>> = points.iter().collect();
+        let tree = KdTree::construct(points.clone());
+        for _ in 0..50 {
+            let q = sample_point(&mut rng, 0.0..100.0);
+            let expected = distances(&q, &everything)[0];
+
+            // exact search
+            let (nearest, _) = tree.approx_nearest(&q, 0.0);
+            assert_eq!(euclid_dist(&q, nearest.unwrap()), expected);
+
+            // approximate search stays within the (1 + eps) bound
+            for eps in [0.1, 0.5, 1.0, 4.0] {
+                let (nearest, visited) = tree.approx_nearest(&q, eps);
+                assert!(euclid_dist(&q, nearest.unwrap()) <= (1.0 + eps) * expected);
+                assert!(visited > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn ut_best_bin_first() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let points = sample_points(&mut rng, 2_000, false);
+        let everything: Vec<&Point<f32>> = points.iter().collect();
+        let tree = KdTree::construct(points.clone());
+        for _ in 0..50 {
+            let q = sample_point(&mut rng, 0.0..100.0);
+            let expected = distances(&q, &everything)[0];
+
+            // a small budget still completes one descent from the root to a leaf
+            let (nearest, visited) = tree.best_bin_first(&q, 1);
+            assert!(nearest.is_some());
+            assert!(visited <= 12);
+
+            // a larger budget can only improve the result
+            let (rough, _) = tree.best_bin_first(&q, 40);
+            assert!(euclid_dist(&q, rough.unwrap()) <= euclid_dist(&q, nearest.unwrap()));
+
+            // an unlimited budget finds the exact nearest point
+            let (nearest, visited) = tree.best_bin_first(&q, usize::MAX);
+            assert_eq!(euclid_dist(&q, nearest.unwrap()), expected);
+            assert!(visited < 2 * 2_000);
+        }
+    }
+
+    /// Generate randomly sampled points in `D` dimensions.
+    fn sample_points_d<const D: usize>(rng: &mut impl Rng, size: usize) -> Vec<[f32; D]> {
+        (0..size)
+            .map(|_| std::array::from_fn(|_| rng.gen::<f32>() * 100.0))
+            .collect()
+    }
+
+    #[test]
+    fn ut_range_query() {
+        let input = vec![
+            Point::from((0.0, 0.0)),
+            Point::from((2.0, 10.0)),
+            Point::from((5.0, 1.0)),
+            Point::from((2.0, 4.0)),
+            Point::from((3.0, 2.0)),
+        ];
+        let tree = KdTree2D::construct(input);
+
+        let mut output = tree.range_query(&Region::new(
+            Point::from((1.0, 1.0)),
+            Point::from((5.0, 4.0)),
+        ));
+        output.sort_by(|a, b| compare_from(*a, *b, 0));
+
+        assert_eq!(
+            output,
+            vec![
+                &Point::from((2.0, 4.0)),
+                &Point::from((3.0, 2.0)),
+                &Point::from((5.0, 1.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn ut_range_query_brute_force() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for grid in [false, true] {
+            let points = sample_points(&mut rng, 500, grid);
+            for rule in [SplitRule::Cycle, SplitRule::MaxSpread] {
+                let tree = KdTree::construct_with(points.clone(), rule);
+                for _ in 0..50 {
+                    let region = sample_region(&mut rng, 0.0..100.0);
+                    let mut expected: Vec<&Point<f32>> =
+                        points.iter().filter(|p| region.contains_point(p)).collect();
+                    expected.sort_by(|a, b| compare_from(*a, *b, 0));
+                    let first: Vec<&Point<f32>> = tree.range_iter(&region).take(3).collect();
+                    assert_eq!(first.len(), expected.len().min(3));
+                    assert!(first.iter().all(|p| region.contains_point(p)));
+                    let mut output = tree.range_query(&region);
+                    output.sort_by(|a, b| compare_from(*a, *b, 0));
+                    assert_eq!(output, expected);
+                }
+            }
+        }
+    }
+
+    /// Checks the points found within `shape` against a linear scan.
+    fn check_shape<S: QueryShape<2>>(tree: &KdTree2D, points: &[Point<f32>], shape: &S) {
+        let mut expected: Vec<&Point<f32>> = points.iter().filter(|p| shape.contains(*p)).collect();
+        expected.sort_by(|a, b| compare_from(*a, *b, 0));
+        let mut output = tree.range_query(shape);
+        output.sort_by(|a, b| compare_from(*a, *b, 0));
+        assert_eq!(output, expected);
+        assert_eq!(tree.range_count(shape), expected.len());
+    }
+
+    #[test]
+    fn ut_shape_query_brute_force() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for grid in [false, true] {
+            let points = sample_points(&mut rng, 500, grid);
+            let tree = KdTree::construct(points.clone());
+            for _ in 0..50 {
+                let (p, q, r) = (
+                    sample_point(&mut rng, 0.0..100.0),
+                    sample_point(&mut rng, 0.0..100.0),
+                    sample_point(&mut rng, 0.0..100.0),
+                );
+                check_shape(&tree, &points, &Circle::new(p, euclid_dist(&p, &q)));
+                check_shape(&tree, &points, &HalfPlane::new(&p, &q));
+                check_shape(&tree, &points, &ConvexPolygon::triangle(p, q, r));
+                // a hexagon centered at p
+                let radius = euclid_dist(&p, &q);
+                let hexagon: Vec<Point<f32>> = (0..6)
+                    .map(|i| {
+                        let angle = i as f32 * std::f32::consts::PI / 3.0;
+                        Point::from((p.x() + radius * angle.cos(), p.y() + radius * angle.sin()))
+                    })
+                    .collect();
+                check_shape(&tree, &points, &ConvexPolygon::new(&hexagon));
+            }
+        }
+    }
+
+    #[test]
+    fn ut_disk_far_from_origin() {
+        let mut rng = StdRng::seed_from_u64(10);
+        let center = Point::from((10000.0, 10000.0));
+        // points on and just around the boundary of the unit circle
+        let mut points = vec![
+            Point::from((10000.0, 10001.1)),
+            Point::from((10000.0, 10000.5)),
+            Point::from((10001.0, 10000.0)),
+            Point::from((10000.0, 9999.0)),
+        ];
+        points.extend((0..500).map(|_| {
+            let angle = rng.gen::<f32>() * std::f32::consts::TAU;
+            let radius = 1.0 + (rng.gen::<f32>() - 0.5) * 0.01;
+            Point::from((
+                center.x() + radius * angle.cos(),
+                center.y() + radius * angle.sin(),
+            ))
+        }));
+        let tree = KdTree::construct(points.clone());
+        for radius in [0.5, 0.995, 1.0, 1.005] {
+            let disk = Circle::new(center, radius);
+            let mut expected = tree.within_radius(&center, radius);
+            expected.sort_by(|a, b| compare_from(*a, *b, 0));
+            let mut output = tree.range_query(&disk);
+            output.sort_by(|a, b| compare_from(*a, *b, 0));
+            assert_eq!(output, expected);
+            check_shape(&tree, &points, &disk);
+        }
+        let output = tree.range_query(&Circle::new(center, 1.0));
+        assert!(!output.contains(&&Point::from((10000.0, 10001.1))));
+        assert!(output.contains(&&Point::from((10001.0, 10000.0))));
+    }
+
+    /// Returns the number of nodes on the longest path from the root to a leaf.
+    fn height<const D: usize, P: Coordinates<D>>(tree: &KdTree<D, P>, node: Child) -> usize {
+        match node {
+            Some(id) => {
+                let n = &tree.nodes[id];
+                1 + height(tree, n.left).max(height(tree, n.right))
+            }
+            None => 0,
+        }
+    }
+
+    #[test]
+    fn ut_insert_remove() {
+        let mut tree = KdTree2D::construct(Vec::new());
+        assert!(tree.is_empty());
+        assert_eq!(tree.nearest(&Point::from((1.0, 1.0))), None);
+
+        let a = tree.insert(Point::from((1.0, 1.0)));
+        tree.insert(Point::from((2.0, 3.0)));
+        tree.insert(Point::from((2.0, 3.0)));
+        assert_eq!(tree.len(), 3);
+        assert_eq!(
+            tree.nearest(&Point::from((0.0, 0.0))),
+            Some(&Point::from((1.0, 1.0)))
+        );
+
+        assert_eq!(tree.remove_handle(a), Some((Point::from((1.0, 1.0)), ())));
+        assert_eq!(tree.remove_handle(a), None);
+        // a stale handle does not remove the point that reuses its slot
+        let b = tree.insert(Point::from((5.0, 5.0)));
+        assert_eq!(tree.remove_handle(b), Some((Point::from((5.0, 5.0)), ())));
+        let c = tree.insert(Point::from((6.0, 6.0)));
+        assert_eq!(c.0, a.0);
+        assert_eq!(tree.remove_handle(a), None);
+        assert_eq!(tree.remove_handle(c), Some((Point::from((6.0, 6.0)), ())));
+        assert_eq!(tree.remove(&Point::from((1.0, 1.0))), None);
+        assert_eq!(tree.remove(&Point::from((2.0, 3.0))), Some(()));
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.remove(&Point::from((2.0, 3.0))), Some(()));
+        assert!(tree.is_empty());
+        assert_eq!(
+            tree.range_query(&Region::new(
+                Point::from((0.0, 0.0)),
+                Point::from((5.0, 5.0))
+            )),
+            Vec::<&Point<f32>>::new()
+        );
+    }
+
+    #[test]
+    fn ut_insert_remove_brute_force() {
+        let mut rng = StdRng::seed_from_u64(6);
+        for grid in [false, true] {
+            for rule in [SplitRule::Cycle, SplitRule::MaxSpread] {
+                // label every point with a unique id to tell duplicates apart
+                let mut items: Vec<(Point<f32>, usize)> = sample_points(&mut rng, 200, grid)
+                    .into_iter()
+                    .zip(0..)
+                    .collect();
+                let mut tree = KdTree::construct_items(items.clone(), rule);
+                let mut handles = Vec::new();
+                for id in 200..2_200 {
+                    match rng.gen_range(0..4) {
+                        // insert a new point
+                        0 | 1 => {
+                            let p = sample_points(&mut rng, 1, grid)[0];
+                            handles.push((tree.insert_item(p, id), (p, id)));
+                            items.push((p, id));
+                        }
+                        // remove an existing point by value
+                        2 => {
+                            if !items.is_empty() {
+                                let (p, _) = items[rng.gen_range(0..items.len())];
+                                // any copy of p may be removed
+                                let id = tree.remove(&p).unwrap();
+                                items.retain(|item| *item != (p, id));
+                                handles.retain(|(_, item)| *item != (p, id));
                             }
                         }
-                        // decide if to traverse the right child (if it exists)
-                        if let Some(rc) = &node.right {
-                            if let Some(upper_region) =
-                                &region.intersect_upper_halfplane(node.data.y())
-                            {
-                                if region.strictly_contains(upper_region) == true {
-                                    result = self.report_subtree(rc, result);
-                                }
-                                // investigate subtree further
-                                else if region.intersects(upper_region) == true {
-                                    result = self.search_tree(region, rc, result, depth + 1)
-                                }
+                        // remove an inserted point by handle
+                        _ => {
+                            if !handles.is_empty() {
+                                let (h, item) =
+                                    handles.swap_remove(rng.gen_range(0..handles.len()));
+                                assert_eq!(tree.remove_handle(h), Some(item));
+                                items.retain(|i| *i != item);
                             }
                         }
                     }
+                    assert_eq!(tree.len(), items.len());
+                }
+
+                for _ in 0..50 {
+                    let region = sample_region(&mut rng, 0.0..100.0);
+                    let mut expected: Vec<usize> = items
+                        .iter()
+                        .filter(|(p, _)| region.contains_point(p))
+                        .map(|(_, id)| *id)
+                        .collect();
+                    expected.sort();
+                    let mut output: Vec<usize> = tree
+                        .range_query_items(&region)
+                        .into_iter()
+                        .map(|(p, id)| {
+                            assert!(items.contains(&(*p, *id)));
+                            *id
+                        })
+                        .collect();
+                    output.sort();
+                    assert_eq!(output, expected);
                 }
             }
         }
-        result
     }
 
-    /// Reports all leaves stored below `node` in the tree using an iterative
-    /// approach.
-    fn report_subtree<'a>(
-        &'a self,
-        node: &'a Node<f32>,
-        mut result: Vec<&'a Point<f32>>,
-    ) -> Vec<&Point<f32>> {
-        let mut stack = vec![node];
-        while stack.is_empty() == false {
-            let n = *stack.pop().as_ref().unwrap();
-            match n.is_leaf() {
-                true => {
-                    result.push(&n.data);
+    #[test]
+    fn ut_items_with_duplicates() {
+        let items = vec![
+            (Point::from((1.0, 1.0)), "a"),
+            (Point::from((3.0, 2.0)), "b"),
+            (Point::from((3.0, 2.0)), "c"),
+            (Point::from((3.0, 2.0)), "d"),
+            (Point::from((8.0, 5.0)), "e"),
+        ];
+        let mut tree: KdTree<2, Point<f32>, &str> =
+            KdTree::construct_items(items, SplitRule::Cycle);
+        fn values<'a>(items: Vec<(&Point<f32>, &&'a str)>) -> Vec<&'a str> {
+            let mut values: Vec<&str> = items.into_iter().map(|(_, v)| *v).collect();
+            values.sort();
+            values
+        }
+        let p = Point::from((3.0, 2.0));
+
+        let region = Region::new(Point::from((2.0, 0.0)), Point::from((4.0, 4.0)));
+        assert_eq!(values(tree.range_query_items(&region)), vec!["b", "c", "d"]);
+        assert_eq!(values(tree.k_nearest_items(&p, 3)), vec!["b", "c", "d"]);
+        assert_eq!(
+            values(tree.within_radius_items(&p, 0.0)),
+            vec!["b", "c", "d"]
+        );
+        assert_eq!(
+            tree.nearest_item(&Point::from((0.0, 0.0))),
+            Some((&Point::from((1.0, 1.0)), &"a"))
+        );
+
+        // a handle removes its own copy among the duplicates
+        let h = tree.insert_item(p, "f");
+        assert_eq!(
+            values(tree.range_query_items(&region)),
+            vec!["b", "c", "d", "f"]
+        );
+        assert_eq!(tree.remove_handle(h), Some((p, "f")));
+        assert_eq!(values(tree.range_query_items(&region)), vec!["b", "c", "d"]);
+
+        let removed = tree.remove(&p).unwrap();
+        let mut expected = vec!["b", "c", "d"];
+        expected.retain(|v| *v != removed);
+        assert_eq!(values(tree.range_query_items(&region)), expected);
+    }
+
+    #[test]
+    fn ut_degenerate_brute_force() {
+        let mut rng = StdRng::seed_from_u64(9);
+        for points in degenerate_points(&mut rng, 200) {
+            let mut inserted = KdTree2D::construct(Vec::new());
+            let handles: Vec<Handle> = points.iter().map(|p| inserted.insert(*p)).collect();
+            for rule in [SplitRule::Cycle, SplitRule::MaxSpread] {
+                let tree = KdTree::construct_with(points.clone(), rule);
+                for tree in [&tree, &inserted] {
+                    for region in grid_regions() {
+                        let mut expected: Vec<&Point<f32>> =
+                            points.iter().filter(|p| region.contains_point(p)).collect();
+                        expected.sort_by(|a, b| compare_from(*a, *b, 0));
+                        let mut output = tree.range_query(&region);
+                        output.sort_by(|a, b| compare_from(*a, *b, 0));
+                        assert_eq!(output, expected);
+                        assert_eq!(tree.range_count(&region), expected.len());
+                    }
+                    let q = Point::from((1.0, 1.0));
+                    let expected = points.iter().filter(|p| euclid_dist(p, &q) <= 1.0).count();
+                    assert_eq!(tree.within_radius(&q, 1.0).len(), expected);
+                    assert_eq!(tree.k_nearest(&q, 300).len(), points.len());
                 }
-                false => {
-                    if let Some(left) = &n.left {
-                        stack.push(left);
+            }
+            // every copy of a duplicate can be removed by handle, and then by value
+            for (i, (p, h)) in points.iter().zip(handles).enumerate() {
+                if i % 2 == 1 {
+                    assert_eq!(inserted.remove_handle(h), Some((*p, ())));
+                    assert_eq!(inserted.remove_handle(h), None);
+                }
+            }
+            for (i, p) in points.iter().enumerate().step_by(2) {
+                assert_eq!(inserted.remove(p), Some(()));
+                assert_eq!(inserted.len(), points.len() - points.len() / 2 - i / 2 - 1);
+            }
+            assert!(inserted.is_empty());
+        }
+    }
+
+    /// Sums, and finds the extremes of, the y-coordinates of the points.
+    struct Heights;
+
+    impl Aggregate<Point<f32>> for Heights {
+        type Value = (Sum, (Min, Max));
+
+        fn weight(p: &Point<f32>, _: &()) -> Self::Value {
+            (Sum(p.y()), (Min(p.y()), Max(p.y())))
+        }
+    }
+
+    #[test]
+    fn ut_range_aggregate_brute_force() {
+        let mut rng = StdRng::seed_from_u64(7);
+        // grid points keep the sums exact
+        let mut points = sample_points(&mut rng, 300, true);
+        let mut tree: KdTree<2, Point<f32>, (), Heights> =
+            KdTree::construct_aggregate(points.clone(), SplitRule::Cycle);
+        for _ in 0..20 {
+            for _ in 0..50 {
+                match rng.gen_bool(0.5) || points.is_empty() {
+                    true => {
+                        let p = sample_points(&mut rng, 1, true)[0];
+                        tree.insert(p);
+                        points.push(p);
                     }
-                    if let Some(right) = &n.right {
-                        stack.push(right);
+                    false => {
+                        let p = points.swap_remove(rng.gen_range(0..points.len()));
+                        assert_eq!(tree.remove(&p), Some(()));
                     }
                 }
             }
+            for _ in 0..20 {
+                let region = Region::new(
+                    Point::from((rng.gen_range(0..16) as f32, rng.gen_range(0..16) as f32)),
+                    Point::from((rng.gen_range(0..16) as f32, rng.gen_range(0..16) as f32)),
+                );
+                let inside: Vec<&Point<f32>> =
+                    points.iter().filter(|p| region.contains_point(p)).collect();
+                assert_eq!(tree.range_count(&region), inside.len());
+
+                let (sum, (min, max)) = tree.range_aggregate(&region);
+                assert_eq!(sum.0, inside.iter().map(|p| p.y()).sum::<f32>());
+                assert_eq!(
+                    min.0,
+                    inside.iter().map(|p| p.y()).fold(f32::INFINITY, f32::min)
+                );
+                assert_eq!(
+                    max.0,
+                    inside
+                        .iter()
+                        .map(|p| p.y())
+                        .fold(f32::NEG_INFINITY, f32::max)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ut_insert_stays_balanced() {
+        let mut tree = KdTree2D::construct(Vec::new());
+        // sorted insertions degenerate into a path without rebalancing
+        for i in 0..1_000 {
+            tree.insert(Point::from((i as f32, i as f32)));
+        }
+        let bound = 1_000_f32.ln() / (1.0 / ALPHA).ln() + 2.0;
+        assert!((height(&tree, tree.root) as f32) <= bound);
+
+        for i in 0..900 {
+            assert_eq!(tree.remove(&Point::from((i as f32, i as f32))), Some(()));
+        }
+        let bound = 100_f32.ln() / (1.0 / ALPHA).ln() + 2.0;
+        assert!((height(&tree, tree.root) as f32) <= bound);
+        assert_eq!(tree.len(), 100);
+    }
+
+    #[test]
+    fn ut_higher_dimensions() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let points = sample_points_d::<4>(&mut rng, 1_000);
+        let everything: Vec<&[f32; 4]> = points.iter().collect();
+        for rule in [SplitRule::Cycle, SplitRule::MaxSpread] {
+            let tree = KdTree::construct_with(points.clone(), rule);
+            for _ in 0..20 {
+                let q: [f32; 4] = std::array::from_fn(|_| rng.gen::<f32>() * 100.0);
+
+                let region = Bounds::new(q, std::array::from_fn(|i| q[i] + 40.0));
+                let expected = points.iter().filter(|p| region.contains_point(*p)).count();
+                assert_eq!(tree.range_query(&region).len(), expected);
+
+                let mut expected: Vec<f32> = everything.iter().map(|p| q.dist(p)).collect();
+                expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                assert_eq!(q.dist(tree.nearest(&q).unwrap()), expected[0]);
+                let k_nearest: Vec<f32> = tree.k_nearest(&q, 5).iter().map(|p| q.dist(p)).collect();
+                assert_eq!(k_nearest, expected[..5]);
+                let (approx, _) = tree.approx_nearest(&q, 0.5);
+                assert!(q.dist(approx.unwrap()) <= 1.5 * expected[0]);
+                let count = expected.iter().filter(|&&d| d <= 30.0).count();
+                assert_eq!(tree.within_radius(&q, 30.0).len(), count);
+            }
         }
-        result
     }
 }
@@ -0,0 +1,385 @@
+/// Project: euclid
+/// Module: layered_range_tree
+///
+/// This file contains functions to construct and query a layered range-tree,
+/// which uses fractional cascading to speed up the range-tree's queries.
+use crate::primitives::*;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+/// Index of a node within the tree's arena of nodes.
+type Child = Option<usize>;
+
+#[derive(Debug, PartialEq)]
+struct Node {
+    data: Point<f32>,
+    left: Child,
+    right: Child,
+    // the position of the node's associated array within the list of entries
+    start: usize,
+    len: usize,
+}
+
+impl Node {
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+
+    /// Returns the positions of the node's associated array within the list of
+    /// entries.
+    fn entries(&self) -> Range<usize> {
+        self.start..self.start + self.len
+    }
+}
+
+/// An element of a node's associated array, which is sorted by y-coordinate.
+#[derive(Debug, PartialEq)]
+struct Entry {
+    // the index of the point's item
+    item: usize,
+    // the number of entries of the left (right) child's array that come before
+    // this entry (the cascading pointers)
+    left: usize,
+    right: usize,
+}
+
+/// A range-tree whose associated structures are arrays sorted by y-coordinate
+/// instead of trees.
+///
+/// Each entry of an array points to where it would fall within the arrays of
+/// the node's children, so the y-range is only searched for once at the root
+/// and then followed down the tree in constant time per node (fractional
+/// cascading).
+///
+/// Both the main tree and the arrays order their points by the composite-number
+/// order of `compare_from`, so points with equal coordinates fall on a
+/// consistent side of each split and at consistent positions in the arrays.
+#[derive(Debug, PartialEq)]
+pub struct LayeredRangeTree<V = ()> {
+    items: Vec<(Point<f32>, V)>,
+    nodes: Vec<Node>,
+    entries: Vec<Entry>,
+    root: usize,
+}
+
+impl LayeredRangeTree {
+    /// Builds a layered range-tree for the set of known points `p`.
+    pub fn construct(points: Vec<Point<f32>>) -> Self {
+        Self::construct_items(points.into_iter().map(|p| (p, ())).collect())
+    }
+}
+
+impl<V> LayeredRangeTree<V> {
+    /// Builds a layered range-tree for the set of known points holding values
+    /// `items`.
+    ///
+    /// - Time complexity: O(n log(n))
+    pub fn construct_items(items: Vec<(Point<f32>, V)>) -> Self {
+        let mut x_sorted: Vec<usize> = (0..items.len()).collect();
+        x_sorted.sort_by(|&a, &b| compare_from(&items[a].0, &items[b].0, 0));
+
+        let mut tree = Self {
+            items,
+            nodes: Vec::new(),
+            entries: Vec::new(),
+            root: 0,
+        };
+        if !x_sorted.is_empty() {
+            tree.root = tree.build(&x_sorted);
+        }
+        tree
+    }
+
+    /// Builds the subtree over the items at the indices `points` (assumes
+    /// points are already sorted by x-coordinate).
+    ///
+    /// The associated array of a node is merged from its children's arrays,
+    /// which also counts the entries of each child that come before each of
+    /// its own entries.
+    fn build(&mut self, points: &[usize]) -> usize {
+        if points.len() == 1 {
+            self.entries.push(Entry {
+                item: points[0],
+                left: 0,
+                right: 0,
+            });
+            self.nodes.push(Node {
+                data: self.items[points[0]].0,
+                left: None,
+                right: None,
+                start: self.entries.len() - 1,
+                len: 1,
+            });
+            return self.nodes.len() - 1;
+        }
+        // partition by the middle index
+        let middle = self.items[points[points.len() / 2]].0;
+        let (left, right) = points.split_at(points.len() / 2);
+        let left = self.build(left);
+        let right = self.build(right);
+
+        // merge the children's arrays by y-coordinate
+        let (l, r) = (self.nodes[left].entries(), self.nodes[right].entries());
+        let (mut i, mut j) = (l.start, r.start);
+        let mut merged = Vec::with_capacity(l.len() + r.len());
+        while i < l.end || j < r.end {
+            let take_left = j == r.end
+                || (i < l.end
+                    && self
+                        .compare_y(self.entries[i].item, self.entries[j].item)
+                        .is_le());
+            let item = match take_left {
+                true => self.entries[i].item,
+                false => self.entries[j].item,
+            };
+            merged.push(Entry {
+                item,
+                left: i - l.start,
+                right: j - r.start,
+            });
+            match take_left {
+                true => i += 1,
+                false => j += 1,
+            }
+        }
+
+        // create a new node with median point to describe the split-line
+        self.nodes.push(Node {
+            data: middle,
+            left: Some(left),
+            right: Some(right),
+            start: self.entries.len(),
+            len: merged.len(),
+        });
+        self.entries.extend(merged);
+        self.nodes.len() - 1
+    }
+
+    /// Compares the points of two items by their composite number on the
+    /// y-axis.
+    fn compare_y(&self, a: usize, b: usize) -> Ordering {
+        compare_from(&self.items[a].0, &self.items[b].0, 1)
+    }
+
+    /// Returns the number of points stored in the tree.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if the tree stores no points.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Asks a rectangular range query question for the rectangle defined by
+    /// `region`.
+    ///
+    /// - Time complexity: O(log(n) + k)
+    pub fn range_query(&self, region: &Region<f32>) -> Vec<&Point<f32>> {
+        self.range_iter(region).collect()
+    }
+
+    /// Asks a rectangular range query question for the rectangle defined by
+    /// `region` to return the points along with their values.
+    pub fn range_query_items(&self, region: &Region<f32>) -> Vec<(&Point<f32>, &V)> {
+        self.range_iter_items(region).collect()
+    }
+
+    /// Iterates over the points contained within the rectangle defined by
+    /// `region`, so the caller can stop early.
+    ///
+    /// The O(log(n)) canonical runs of entries are selected up front, while
+    /// their points are only visited as the iterator advances.
+    pub fn range_iter(&self, region: &Region<f32>) -> impl Iterator<Item = &Point<f32>> + '_ {
+        self.range_iter_items(region).map(|(p, _)| p)
+    }
+
+    /// Iterates over the points contained within the rectangle defined by
+    /// `region` along with their values, so the caller can stop early.
+    pub fn range_iter_items(
+        &self,
+        region: &Region<f32>,
+    ) -> impl Iterator<Item = (&Point<f32>, &V)> + '_ {
+        let mut runs = Vec::new();
+        self.search_tree(region, &mut |run| runs.push(run));
+        runs.into_iter().flat_map(move |run| {
+            self.entries[run].iter().map(|e| {
+                let (p, v) = &self.items[e.item];
+                (p, v)
+            })
+        })
+    }
+
+    /// Counts the points contained within the rectangle defined by `region`.
+    ///
+    /// - Time complexity: O(log(n))
+    pub fn range_count(&self, region: &Region<f32>) -> usize {
+        let mut count = 0;
+        self.search_tree(region, &mut |run| count += run.len());
+        count
+    }
+
+    /// Follows the cascading pointers to find the position within the array
+    /// of the child `child` (either the left or right child) of `node` that
+    /// corresponds to the position `pos` within the array of `node`.
+    fn cascade(&self, node: &Node, pos: usize, child: Child) -> usize {
+        let child = child.unwrap();
+        match pos == node.len {
+            // every entry of the child comes before the end of the array
+            true => self.nodes[child].len,
+            false => {
+                let e = &self.entries[node.start + pos];
+                match Some(child) == node.left {
+                    true => e.left,
+                    false => e.right,
+                }
+            }
+        }
+    }
+
+    /// Moves down from `node` to its child `child` along with the positions
+    /// `span` of the y-range within the arrays.
+    fn descend(&self, node: &Node, span: Range<usize>, child: Child) -> (&Node, Range<usize>) {
+        let lo = self.cascade(node, span.start, child);
+        let hi = self.cascade(node, span.end, child);
+        (&self.nodes[child.unwrap()], lo..hi)
+    }
+
+    /// Traverses down the tree to select the canonical subsets of the points
+    /// that are defined within the x-axis range. The positions of the y-axis
+    /// range are only searched for in the root's array and are then carried
+    /// down to each subset, which reports its run of entries to `report`.
+    fn search_tree(&self, region: &Region<f32>, report: &mut impl FnMut(Range<usize>)) {
+        if self.is_empty() {
+            return;
+        }
+        let root = &self.nodes[self.root];
+        let array = &self.entries[root.entries()];
+        let lo = array.partition_point(|e| self.items[e.item].0.y() < region.l_y());
+        let hi = array.partition_point(|e| self.items[e.item].0.y() <= region.r_y());
+
+        // report the entries of a leaf whose point is within the range
+        let report_leaf = |v: &Node, report: &mut dyn FnMut(Range<usize>)| {
+            if region.contains_point(&v.data) {
+                report(v.entries());
+            }
+        };
+
+        // find the vertex that splits the x-axis range
+        let (mut v, mut span) = (root, lo..hi);
+        while !v.is_leaf() && (v.data.x() > region.r_x() || v.data.x() < region.l_x()) {
+            let child = match v.data.x() > region.r_x() {
+                // too far right... try to go left
+                true => v.left,
+                // too far left... try to go right
+                false => v.right,
+            };
+            (v, span) = self.descend(v, span, child);
+        }
+        if v.is_leaf() {
+            return report_leaf(v, report);
+        }
+        let (v_split, split_span) = (v, span);
+
+        // follow the path to leftmost boundary and report the runs right of the path
+        let (mut v, mut span) = self.descend(v_split, split_span.clone(), v_split.left);
+        while !v.is_leaf() {
+            if v.data.x() >= region.l_x() {
+                let (right, run) = self.descend(v, span.clone(), v.right);
+                report(right.start + run.start..right.start + run.end);
+                (v, span) = self.descend(v, span, v.left);
+            } else {
+                (v, span) = self.descend(v, span, v.right);
+            }
+        }
+        report_leaf(v, report);
+
+        // follow the path to rightmost boundary and report the runs left of the path
+        let (mut v, mut span) = self.descend(v_split, split_span, v_split.right);
+        while !v.is_leaf() {
+            if v.data.x() <= region.r_x() {
+                let (left, run) = self.descend(v, span.clone(), v.left);
+                report(left.start + run.start..left.start + run.end);
+                (v, span) = self.descend(v, span, v.right);
+            } else {
+                (v, span) = self.descend(v, span, v.left);
+            }
+        }
+        report_leaf(v, report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Sorts the points by increasing x-coordinate, and then increasing y-coordinate.
+    fn sorted(mut points: Vec<&Point<f32>>) -> Vec<&Point<f32>> {
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        points
+    }
+
+    #[test]
+    fn ut_range_query_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let points: Vec<Point<f32>> = (0..500)
+            .map(|_| Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)))
+            .collect();
+        let tree = LayeredRangeTree::construct(points.clone());
+        for _ in 0..50 {
+            let region = Region::new(
+                Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)),
+                Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)),
+            );
+            let expected: Vec<&Point<f32>> =
+                points.iter().filter(|p| region.contains_point(p)).collect();
+            assert_eq!(tree.range_count(&region), expected.len());
+            assert_eq!(sorted(tree.range_query(&region)), sorted(expected));
+        }
+    }
+
+    #[test]
+    fn ut_items_with_duplicates() {
+        let mut rng = StdRng::seed_from_u64(2);
+        // grid points with many duplicate coordinates, labelled by a unique id
+        let items: Vec<(Point<f32>, usize)> = (0..300)
+            .map(|id| {
+                let p = Point::from((rng.gen_range(0..8) as f32, rng.gen_range(0..8) as f32));
+                (p, id)
+            })
+            .collect();
+        let tree = LayeredRangeTree::construct_items(items.clone());
+        for _ in 0..50 {
+            let region = Region::new(
+                Point::from((rng.gen_range(0..8) as f32, rng.gen_range(0..8) as f32)),
+                Point::from((rng.gen_range(0..8) as f32, rng.gen_range(0..8) as f32)),
+            );
+            let mut expected: Vec<usize> = items
+                .iter()
+                .filter(|(p, _)| region.contains_point(p))
+                .map(|(_, id)| *id)
+                .collect();
+            expected.sort();
+            let mut output: Vec<usize> = tree
+                .range_query_items(&region)
+                .into_iter()
+                .map(|(p, id)| {
+                    assert_eq!(*p, items[*id].0);
+                    *id
+                })
+                .collect();
+            output.sort();
+            assert_eq!(output, expected);
+            assert_eq!(tree.range_count(&region), expected.len());
+        }
+    }
+
+    #[test]
+    fn ut_empty() {
+        let tree = LayeredRangeTree::construct(Vec::new());
+        let region = Region::new(Point::from((0.0, 0.0)), Point::from((1.0, 1.0)));
+        assert_eq!(tree.range_query(&region), Vec::<&Point<f32>>::new());
+        assert_eq!(tree.range_count(&region), 0);
+    }
+}
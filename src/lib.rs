@@ -4,10 +4,25 @@
 /// This file contains geometric primitives for performing computations in
 /// 2-dimensional space.
 
+pub mod aggregate;
+pub mod bvh;
 pub mod convex_hull;
+pub mod enclosing_circle;
+#[cfg(test)]
+mod fixtures;
+pub mod grid;
+pub mod interval_tree;
 pub mod kd_tree;
+pub mod layered_range_tree;
 pub mod primitives;
+pub mod priority_search_tree;
+pub mod proximity;
+pub mod quadtree;
+pub mod query_shape;
 pub mod range_tree;
+pub mod rtree;
+pub mod segment_tree;
+pub mod skyline;
 pub mod staircase;
 pub mod point_loc;
 
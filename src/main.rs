@@ -1,5 +1,6 @@
 use euclid::point_loc::trapezoidal_map;
-use euclid::primitives::Region;
+use euclid::primitives::{Point, Region};
+use euclid::priority_search_tree::{PrioritySearchTree, ThreeSided};
 use euclid::{self, kd_tree::KdTree, range_tree::RangeTree};
 use std::env;
 
@@ -36,6 +37,23 @@ fn main() {
             // write results
             euclid::write_points(&args.next().unwrap(), stairs);
         }
+        "layers" => {
+            // read inputs
+            let points = euclid::read_points(&args.next().unwrap());
+            // compute the dominance layer of every point
+            let layers = euclid::staircase::maximal_layers(&points);
+            // write each layer to its own file from left to right
+            let prefix = args.next().unwrap();
+            let count = layers.iter().max().map_or(0, |l| l + 1);
+            let mut groups: Vec<Vec<&Point<f32>>> = vec![Vec::new(); count];
+            for (p, &l) in points.iter().zip(&layers) {
+                groups[l].push(p);
+            }
+            for (layer, mut stairs) in groups.into_iter().enumerate() {
+                stairs.sort_by(|a, b| a.x().partial_cmp(&b.x()).unwrap());
+                euclid::write_points(&format!("{}{}.txt", prefix, layer), stairs);
+            }
+        }
         "kd" => {
             // read point set
             let points = euclid::read_points::<f32>(&args.next().unwrap());
@@ -66,6 +84,22 @@ fn main() {
             // write results
             euclid::write_points::<f32>(&args.next().unwrap(), result);
         }
+        "pst" => {
+            // read point set
+            let points = euclid::read_points::<f32>(&args.next().unwrap());
+            // read range (the x-range between both points, above the lowest one)
+            let range = {
+                let range = euclid::read_points::<f32>(&args.next().unwrap());
+                let (p, q) = (range.get(0).unwrap(), range.get(1).unwrap());
+                ThreeSided::new(p.x(), q.x(), p.y().min(q.y()))
+            };
+            // construct priority search tree
+            let pst = PrioritySearchTree::construct(points);
+            // perform three-sided range query on priority search tree
+            let result = pst.range_query(&range);
+            // write results
+            euclid::write_points::<f32>(&args.next().unwrap(), result);
+        }
         "trapmap" => {
             // read line segments
             let segments = euclid::read_segments(&args.next().unwrap());
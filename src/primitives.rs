@@ -4,6 +4,7 @@
 ///
 /// This file contains geometric primitives for performing computations in
 /// 2-dimensional space.
+use std::cmp::Ordering;
 
 #[derive(Debug, PartialEq)]
 pub enum Orientation {
@@ -190,6 +191,13 @@ impl<T: Default + Copy + std::cmp::PartialOrd> Region<T> {
     }
 }
 
+impl From<&LineSegment> for Region<f32> {
+    /// Creates the bounding box of the line segment.
+    fn from(s: &LineSegment) -> Self {
+        Region::new(*s.start(), *s.end())
+    }
+}
+
 impl<T: Default + Copy> From<(T, T)> for Point<T> {
     fn from(pair: (T, T)) -> Self {
         Self(pair.0, pair.1)
@@ -219,6 +227,134 @@ impl std::fmt::Display for Point<f32> {
     }
 }
 
+/// A location in `D`-dimensional space that exposes its coordinates by axis.
+pub trait Coordinates<const D: usize>: Copy {
+    /// Returns the coordinate found on the given `axis` (0 for the x-axis).
+    fn coord(&self, axis: usize) -> Coordinate;
+
+    /// Computes the euclidean distance to another point.
+    fn dist(&self, other: &Self) -> Coordinate {
+        let mut sum = 0.0;
+        for axis in 0..D {
+            sum += f32::powi(self.coord(axis) - other.coord(axis), 2);
+        }
+        f32::sqrt(sum)
+    }
+}
+
+impl Coordinates<2> for Point<f32> {
+    fn coord(&self, axis: usize) -> Coordinate {
+        match axis {
+            0 => self.x(),
+            _ => self.y(),
+        }
+    }
+
+    fn dist(&self, other: &Self) -> Coordinate {
+        euclid_dist(self, other)
+    }
+}
+
+impl<const D: usize> Coordinates<D> for [Coordinate; D] {
+    fn coord(&self, axis: usize) -> Coordinate {
+        self[axis]
+    }
+}
+
+/// Compares two points by their coordinates starting from `axis` and cycling
+/// through the remaining axes (if equal coordinates).
+///
+/// This is the composite-number order (de Berg et al.), which the spatial
+/// trees use to break ties between equal coordinates: a point `(a, b)` is
+/// treated as the composite number `(a|b)` on the x-axis and `(b|a)` on the
+/// y-axis, so distinct points are never equal along any axis.
+pub fn compare_from<P: Coordinates<D>, const D: usize>(a: &P, b: &P, axis: usize) -> Ordering {
+    for i in 0..D {
+        let axis = (axis + i) % D;
+        match a.coord(axis).partial_cmp(&b.coord(axis)).unwrap() {
+            Ordering::Equal => continue,
+            ordering => return ordering,
+        }
+    }
+    Ordering::Equal
+}
+
+/// An axis-aligned box in `D`-dimensional space defined by its lowest and
+/// highest coordinate along each axis (inclusive).
+///
+/// This is the `D`-dimensional counterpart to `Region`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bounds<const D: usize> {
+    min: [Coordinate; D],
+    max: [Coordinate; D],
+}
+
+impl<const D: usize> Bounds<D> {
+    /// Creates the box spanned by the two opposite corners `start` and `end`.
+    pub fn new(start: [Coordinate; D], end: [Coordinate; D]) -> Self {
+        let mut min = start;
+        let mut max = end;
+        for axis in 0..D {
+            if min[axis] > max[axis] {
+                std::mem::swap(&mut min[axis], &mut max[axis]);
+            }
+        }
+        Self { min, max }
+    }
+
+    /// Creates a box that covers all of space.
+    pub fn everything() -> Self {
+        Self {
+            min: [Coordinate::NEG_INFINITY; D],
+            max: [Coordinate::INFINITY; D],
+        }
+    }
+
+    /// Returns the lowest coordinate value along `axis`.
+    pub fn min(&self, axis: usize) -> Coordinate {
+        self.min[axis]
+    }
+
+    /// Returns the highest coordinate value along `axis`.
+    pub fn max(&self, axis: usize) -> Coordinate {
+        self.max[axis]
+    }
+
+    /// Checks if a point `p` is within the box (including the boundary).
+    pub fn contains_point<P: Coordinates<D>>(&self, p: &P) -> bool {
+        (0..D).all(|axis| self.min[axis] <= p.coord(axis) && p.coord(axis) <= self.max[axis])
+    }
+
+    /// Checks if `b` is fully contained within the box defined by `self`.
+    pub fn contains_bounds(&self, b: &Bounds<D>) -> bool {
+        (0..D).all(|axis| self.min[axis] <= b.min[axis] && b.max[axis] <= self.max[axis])
+    }
+
+    /// Checks if the box shares at least one point with `b`.
+    pub fn intersects(&self, b: &Bounds<D>) -> bool {
+        (0..D).all(|axis| self.min[axis] <= b.max[axis] && b.min[axis] <= self.max[axis])
+    }
+
+    /// Splits the box by the hyperplane orthogonal to `axis` at `value` into
+    /// the lower and upper boxes (both keep the splitting boundary).
+    pub fn split(&self, axis: usize, value: Coordinate) -> (Bounds<D>, Bounds<D>) {
+        let mut lower = *self;
+        let mut upper = *self;
+        lower.max[axis] = value.min(self.max[axis]);
+        upper.min[axis] = value.max(self.min[axis]);
+        (lower, upper)
+    }
+}
+
+impl From<Region<f32>> for Bounds<2> {
+    fn from(region: Region<f32>) -> Self {
+        Self {
+            min: [region.l_x(), region.l_y()],
+            max: [region.r_x(), region.r_y()],
+        }
+    }
+}
+
 /// A line consisting of a start point and an end point.
 #[derive(Debug, PartialEq)]
 pub struct LineSegment(Point<f32>, Point<f32>);
@@ -233,7 +369,7 @@ impl std::fmt::Display for LineSegment {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} to {}", self.0, self.1)
     }
-}  
+}
 
 impl LineSegment {
     /// Returns the minimum value across the x-axis (left-most).
@@ -283,6 +419,64 @@ impl LineSegment {
     }
 }
 
+/// A circle defined by its center point and radius.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Circle {
+    center: Point<f32>,
+    radius: f32,
+}
+
+impl Circle {
+    /// Creates the circle around `center` with the non-negative `radius`.
+    pub fn new(center: Point<f32>, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Creates the smallest circle passing through both points `p` and `q`.
+    pub fn from_diameter(p: &Point<f32>, q: &Point<f32>) -> Self {
+        let center = Point::from(((p.x() + q.x()) / 2.0, (p.y() + q.y()) / 2.0));
+        Self {
+            center,
+            radius: euclid_dist(p, q) / 2.0,
+        }
+    }
+
+    /// Creates the circle passing through all three points `p`, `q`, and `r`.
+    ///
+    /// Returns `None` if the points are collinear.
+    pub fn circumscribe(p: &Point<f32>, q: &Point<f32>, r: &Point<f32>) -> Option<Self> {
+        // translate to p and solve in double precision to limit rounding error
+        let (bx, by) = ((q.x() - p.x()) as f64, (q.y() - p.y()) as f64);
+        let (cx, cy) = ((r.x() - p.x()) as f64, (r.y() - p.y()) as f64);
+        let det = 2.0 * (bx * cy - by * cx);
+        if det == 0.0 {
+            return None;
+        }
+        let (b, c) = (bx * bx + by * by, cx * cx + cy * cy);
+        let ux = (cy * b - by * c) / det;
+        let uy = (bx * c - cx * b) / det;
+        Some(Self {
+            center: Point::from(((ux + p.x() as f64) as f32, (uy + p.y() as f64) as f32)),
+            radius: (ux * ux + uy * uy).sqrt() as f32,
+        })
+    }
+
+    /// Returns the point at the center of the circle.
+    pub fn center(&self) -> &Point<f32> {
+        &self.center
+    }
+
+    /// Returns the distance from the center to the boundary of the circle.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Checks if a point `p` is inside or on the boundary of the circle.
+    pub fn contains_point(&self, p: &Point<f32>) -> bool {
+        euclid_dist(&self.center, p) <= self.radius
+    }
+}
+
 /// Computes the cross-product among the set of points.
 ///
 /// Equation: (`p1` - `p0`) x (`p2` - `p0`)
@@ -464,4 +658,16 @@ mod tests {
         let result = segments_intersect(&line1, &line2);
         assert_eq!(result, false);
     }
+
+    #[test]
+    fn ut_circle_contains_point() {
+        let circle = Circle::new(Point(0.0, 0.0), 5.0);
+        assert!(circle.contains_point(&Point(3.0, 4.0)));
+        assert!(!circle.contains_point(&Point(3.0, 4.001)));
+
+        // the boundary is exact far from the origin
+        let circle = Circle::new(Point(10000.0, 10000.0), 1.0);
+        assert!(circle.contains_point(&Point(10000.0, 10001.0)));
+        assert!(!circle.contains_point(&Point(10000.0, 10001.001)));
+    }
 }
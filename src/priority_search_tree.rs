@@ -0,0 +1,261 @@
+/// Project: euclid
+/// Module: priority_search_tree
+///
+/// This file contains functions to construct and query a priority search tree
+/// data structure, which answers three-sided range queries.
+use crate::primitives::*;
+
+/// Index of a node within the tree's arena of nodes.
+type Child = Option<usize>;
+
+#[derive(Debug, PartialEq)]
+struct Node {
+    // the highest point within the subtree and the index of its item
+    data: Point<f32>,
+    item: usize,
+    // the x-coordinate of the median of the remaining points to describe the
+    // split-line (unused by leaves)
+    split: f32,
+    left: Child,
+    right: Child,
+}
+
+impl Node {
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+}
+
+/// A three-sided range `[x_min, x_max] × [y_min, +inf)` that is unbounded
+/// from above.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThreeSided {
+    x_min: f32,
+    x_max: f32,
+    y_min: f32,
+}
+
+impl ThreeSided {
+    /// Creates the range between the vertical lines through `x0` and `x1`
+    /// (in either order), above the horizontal line through `y_min`.
+    pub fn new(x0: f32, x1: f32, y_min: f32) -> Self {
+        Self {
+            x_min: x0.min(x1),
+            x_max: x0.max(x1),
+            y_min,
+        }
+    }
+
+    /// Checks if a point `p` is within the range (including the boundary).
+    pub fn contains_point(&self, p: &Point<f32>) -> bool {
+        self.x_min <= p.x() && p.x() <= self.x_max && self.y_min <= p.y()
+    }
+}
+
+/// An iterator over the points of a priority search tree within a
+/// three-sided range, which walks down the tree with an explicit stack.
+struct ThreeSidedPoints<'a, V> {
+    items: &'a [(Point<f32>, V)],
+    nodes: &'a [Node],
+    range: ThreeSided,
+    stack: Vec<usize>,
+}
+
+impl<'a, V> Iterator for ThreeSidedPoints<'a, V> {
+    type Item = (&'a Point<f32>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(id) = self.stack.pop() {
+            let node = &self.nodes[id];
+            // every point below is lower than the highest point of the subtree
+            if node.data.y() < self.range.y_min {
+                continue;
+            }
+            if !node.is_leaf() {
+                // points left of the split-line are at or before it along the x-axis
+                if self.range.x_max >= node.split {
+                    self.stack.extend(node.right);
+                }
+                if self.range.x_min <= node.split {
+                    self.stack.extend(node.left);
+                }
+            }
+            if self.range.contains_point(&node.data) {
+                let (p, v) = &self.items[node.item];
+                return Some((p, v));
+            }
+        }
+        None
+    }
+}
+
+/// A priority search tree over points in the plane.
+///
+/// Each node stores the highest remaining point of its subtree (a max-heap on
+/// the y-coordinate), and splits the other points by their median along the
+/// x-axis (a search tree on the x-coordinate), using the composite-number
+/// order of `compare_from` to break ties. This answers queries of the form
+/// `x in [a, b], y >= c` in O(log(n) + k) time with O(n) space.
+#[derive(Debug, PartialEq)]
+pub struct PrioritySearchTree<V = ()> {
+    items: Vec<(Point<f32>, V)>,
+    nodes: Vec<Node>,
+    root: Child,
+}
+
+impl PrioritySearchTree {
+    /// Builds a priority search tree for the set of known points `p`.
+    pub fn construct(points: Vec<Point<f32>>) -> Self {
+        Self::construct_items(points.into_iter().map(|p| (p, ())).collect())
+    }
+}
+
+impl<V> PrioritySearchTree<V> {
+    /// Builds a priority search tree for the set of known points holding values
+    /// `items`.
+    ///
+    /// - Time complexity: O(n log(n))
+    pub fn construct_items(items: Vec<(Point<f32>, V)>) -> Self {
+        let mut x_sorted: Vec<usize> = (0..items.len()).collect();
+        x_sorted.sort_by(|&a, &b| compare_from(&items[a].0, &items[b].0, 0));
+
+        let mut nodes = Vec::with_capacity(items.len());
+        let root = Self::build(&mut nodes, &items, x_sorted);
+        Self { items, nodes, root }
+    }
+
+    /// Recursive function call to build the subtree over the items at the
+    /// indices `points` (assumes points are already sorted by x-coordinate).
+    fn build(nodes: &mut Vec<Node>, items: &[(Point<f32>, V)], mut points: Vec<usize>) -> Child {
+        if points.is_empty() {
+            return None;
+        }
+        // take out the highest point (the lowest index among equal heights)
+        let mut top = 0;
+        for i in 1..points.len() {
+            if items[points[i]].0.y() > items[points[top]].0.y() {
+                top = i;
+            }
+        }
+        let item = points.remove(top);
+
+        // partition the remaining points by the median index
+        let middle = points.len() / 2;
+        let split = points.get(middle).map_or(0.0, |&i| items[i].0.x());
+        let right = points.split_off(middle);
+
+        let id = nodes.len();
+        nodes.push(Node {
+            data: items[item].0,
+            item,
+            split,
+            left: None,
+            right: None,
+        });
+        nodes[id].left = Self::build(nodes, items, points);
+        nodes[id].right = Self::build(nodes, items, right);
+        Some(id)
+    }
+
+    /// Returns the number of points stored in the tree.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if the tree stores no points.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Asks a three-sided range query question for the points within `range`.
+    ///
+    /// - Time complexity: O(log(n) + k)
+    pub fn range_query(&self, range: &ThreeSided) -> Vec<&Point<f32>> {
+        self.range_iter(range).collect()
+    }
+
+    /// Asks a three-sided range query question for the points within `range`
+    /// to return the points along with their values.
+    pub fn range_query_items(&self, range: &ThreeSided) -> Vec<(&Point<f32>, &V)> {
+        self.range_iter_items(range).collect()
+    }
+
+    /// Iterates over the points contained within `range` while lazily walking
+    /// down the tree, so the caller can stop early.
+    pub fn range_iter(&self, range: &ThreeSided) -> impl Iterator<Item = &Point<f32>> + '_ {
+        self.range_iter_items(range).map(|(p, _)| p)
+    }
+
+    /// Iterates over the points contained within `range` along with their
+    /// values while lazily walking down the tree.
+    pub fn range_iter_items(
+        &self,
+        range: &ThreeSided,
+    ) -> impl Iterator<Item = (&Point<f32>, &V)> + '_ {
+        ThreeSidedPoints {
+            items: &self.items,
+            nodes: &self.nodes,
+            range: *range,
+            stack: self.root.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Sorts the points by increasing x-coordinate, and then increasing y-coordinate.
+    fn sorted(mut points: Vec<&Point<f32>>) -> Vec<&Point<f32>> {
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        points
+    }
+
+    #[test]
+    fn ut_range_query() {
+        let tree = PrioritySearchTree::construct(vec![
+            Point::from((0.0, 0.0)),
+            Point::from((2.0, 10.0)),
+            Point::from((5.0, 1.0)),
+            Point::from((2.0, 4.0)),
+            Point::from((3.0, 2.0)),
+        ]);
+        let output = tree.range_query(&ThreeSided::new(1.0, 5.0, 2.0));
+        assert_eq!(
+            sorted(output),
+            vec![
+                &Point::from((2.0, 4.0)),
+                &Point::from((2.0, 10.0)),
+                &Point::from((3.0, 2.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn ut_range_query_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for grid in [false, true] {
+            let mut sample = || match grid {
+                true => rng.gen_range(0..8) as f32,
+                false => rng.gen::<f32>() * 100.0,
+            };
+            let points: Vec<Point<f32>> = (0..500)
+                .map(|_| Point::from((sample(), sample())))
+                .collect();
+            let tree = PrioritySearchTree::construct(points.clone());
+            for _ in 0..50 {
+                let range = ThreeSided::new(sample(), sample(), sample());
+                let expected: Vec<&Point<f32>> =
+                    points.iter().filter(|p| range.contains_point(p)).collect();
+                assert_eq!(sorted(tree.range_query(&range)), sorted(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn ut_empty() {
+        let tree = PrioritySearchTree::construct(Vec::new());
+        assert!(tree.range_query(&ThreeSided::new(0.0, 1.0, 0.0)).is_empty());
+    }
+}
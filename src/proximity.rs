@@ -0,0 +1,312 @@
+/// Project: euclid
+/// Module: proximity
+///
+/// This file contains geometric algorithms for answering proximity questions,
+/// such as finding the closest pair among a set of points.
+use crate::primitives::*;
+use rand::seq::SliceRandom;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A pair of indices into a set of points and the distance between them.
+pub type Pair = (usize, usize, f32);
+
+/// Computes the closest pair of points among `points` by comparing every pair.
+///
+/// Returns `None` when there are fewer than two points.
+///
+/// - Time complexity: O(n^2)
+pub fn brute_force(points: &[Point<f32>]) -> Option<Pair> {
+    let mut best: Option<Pair> = None;
+    for i in 0..points.len() {
+        for j in (i + 1)..points.len() {
+            let d = euclid_dist(&points[i], &points[j]);
+            if best.is_none_or(|b| d < b.2) {
+                best = Some((i, j, d));
+            }
+        }
+    }
+    best
+}
+
+/// Computes the closest pair of points among `points` by recursively splitting
+/// the points at the median x-coordinate and checking the strip along the
+/// split-line.
+///
+/// Returns `None` when there are fewer than two points. Duplicate points are
+/// reported as a pair at distance zero.
+///
+/// - Time complexity: O(n*log(n))
+pub fn closest_pair(points: &[Point<f32>]) -> Option<Pair> {
+    // sort the point indices by increasing x-coordinate, and then increasing y-coordinate (if equal x-coordinate)
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(
+        |&a, &b| match points[a].x().partial_cmp(&points[b].x()).unwrap() {
+            Ordering::Equal => points[a].y().partial_cmp(&points[b].y()).unwrap(),
+            ordering => ordering,
+        },
+    );
+    let mut buffer = vec![0; points.len()];
+    divide(points, &mut order, &mut buffer).map(ordered)
+}
+
+/// Recursive function call to find the closest pair among the points in
+/// `order` (assumes they are sorted by x-coordinate).
+///
+/// On return, `order` is sorted by increasing y-coordinate.
+fn divide(points: &[Point<f32>], order: &mut [usize], buffer: &mut [usize]) -> Option<Pair> {
+    if order.len() <= 3 {
+        let best = brute_force_subset(points, order);
+        order.sort_by(|&a, &b| points[a].y().partial_cmp(&points[b].y()).unwrap());
+        return best;
+    }
+    // partition by the middle index
+    let middle = order.len() / 2;
+    let split_x = points[order[middle]].x();
+    let best = {
+        let (left, right) = order.split_at_mut(middle);
+        let (left_buf, right_buf) = buffer.split_at_mut(middle);
+        closer(
+            divide(points, left, left_buf),
+            divide(points, right, right_buf),
+        )
+    };
+
+    // merge both halves by increasing y-coordinate
+    let (mut i, mut j) = (0, middle);
+    for slot in buffer[..order.len()].iter_mut() {
+        let take_left =
+            j == order.len() || (i < middle && points[order[i]].y() <= points[order[j]].y());
+        match take_left {
+            true => {
+                *slot = order[i];
+                i += 1;
+            }
+            false => {
+                *slot = order[j];
+                j += 1;
+            }
+        }
+    }
+    order.copy_from_slice(&buffer[..order.len()]);
+
+    // collect the points within the strip around the split-line
+    let mut best = best;
+    let delta = best.map_or(f32::INFINITY, |b| b.2);
+    let strip: Vec<usize> = order
+        .iter()
+        .copied()
+        .filter(|&k| (points[k].x() - split_x).abs() <= delta)
+        .collect();
+    // only the few points above p within the strip can be closer than delta
+    for (a, &p) in strip.iter().enumerate() {
+        for &q in &strip[(a + 1)..] {
+            let delta = best.map_or(f32::INFINITY, |b| b.2);
+            if points[q].y() - points[p].y() > delta {
+                break;
+            }
+            let d = euclid_dist(&points[p], &points[q]);
+            if d < delta {
+                best = Some((p, q, d));
+            }
+        }
+    }
+    best
+}
+
+/// Computes the closest pair of points among `points` by inserting the points
+/// in random order into a uniform grid whose cell size matches the closest
+/// distance found so far.
+///
+/// Returns `None` when there are fewer than two points. Duplicate points are
+/// reported as a pair at distance zero.
+///
+/// - Time complexity: O(n) expected
+pub fn closest_pair_grid(points: &[Point<f32>]) -> Option<Pair> {
+    if points.len() < 2 {
+        return None;
+    }
+    // compute random permutation of the points
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.shuffle(&mut rand::thread_rng());
+
+    let mut best = (
+        order[0],
+        order[1],
+        euclid_dist(&points[order[0]], &points[order[1]]),
+    );
+    let mut grid = Grid::new(best.2);
+    grid.insert(points, order[0]);
+    grid.insert(points, order[1]);
+
+    for (i, &p) in order.iter().enumerate().skip(2) {
+        if best.2 == 0.0 {
+            break;
+        }
+        match grid.closest(points, p) {
+            // a closer pair invalidates the current cell size
+            Some((q, d)) if d < best.2 => {
+                best = (q, p, d);
+                if d > 0.0 {
+                    grid = Grid::new(d);
+                    for &k in &order[..=i] {
+                        grid.insert(points, k);
+                    }
+                }
+            }
+            _ => grid.insert(points, p),
+        }
+    }
+    Some(ordered(best))
+}
+
+/// A uniform grid of square cells that buckets point indices by location.
+struct Grid {
+    size: f32,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Grid {
+    fn new(size: f32) -> Self {
+        Self {
+            size,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Returns the coordinates of the cell that contains the point `p`.
+    fn cell(&self, p: &Point<f32>) -> (i64, i64) {
+        (
+            (p.x() / self.size).floor() as i64,
+            (p.y() / self.size).floor() as i64,
+        )
+    }
+
+    fn insert(&mut self, points: &[Point<f32>], i: usize) {
+        self.cells.entry(self.cell(&points[i])).or_default().push(i);
+    }
+
+    /// Finds the closest indexed point to the point at index `i` among the
+    /// cells neighboring its own cell.
+    fn closest(&self, points: &[Point<f32>], i: usize) -> Option<(usize, f32)> {
+        let (cx, cy) = self.cell(&points[i]);
+        let mut best: Option<(usize, f32)> = None;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let Some(bucket) = self.cells.get(&(cx + dx, cy + dy)) else {
+                    continue;
+                };
+                for &j in bucket {
+                    let d = euclid_dist(&points[i], &points[j]);
+                    if best.is_none_or(|b| d < b.1) {
+                        best = Some((j, d));
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Computes the closest pair among the points at the indices `subset`.
+fn brute_force_subset(points: &[Point<f32>], subset: &[usize]) -> Option<Pair> {
+    let mut best: Option<Pair> = None;
+    for (a, &i) in subset.iter().enumerate() {
+        for &j in &subset[(a + 1)..] {
+            let d = euclid_dist(&points[i], &points[j]);
+            if best.is_none_or(|b| d < b.2) {
+                best = Some((i, j, d));
+            }
+        }
+    }
+    best
+}
+
+/// Selects the pair with the shorter distance.
+fn closer(a: Option<Pair>, b: Option<Pair>) -> Option<Pair> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.2 < a.2 { b } else { a }),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+/// Orders the indices within the pair from lowest to highest.
+fn ordered(pair: Pair) -> Pair {
+    (pair.0.min(pair.1), pair.0.max(pair.1), pair.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn check_against_brute_force(points: &[Point<f32>]) {
+        let expected = brute_force(points).unwrap();
+        for (i, j, d) in [
+            closest_pair(points).unwrap(),
+            closest_pair_grid(points).unwrap(),
+        ] {
+            assert!(i < j);
+            assert_eq!(d, expected.2);
+            assert_eq!(euclid_dist(&points[i], &points[j]), d);
+        }
+    }
+
+    #[test]
+    fn ut_closest_pair() {
+        let input = vec![
+            Point::from((0.0, 0.0)),
+            Point::from((2.0, 10.0)),
+            Point::from((6.0, 1.0)),
+            Point::from((2.0, 4.0)),
+            Point::from((3.0, 2.0)),
+        ];
+
+        let expected = Some((3, 4, euclid_dist(&input[3], &input[4])));
+        assert_eq!(closest_pair(&input), expected);
+        assert_eq!(closest_pair_grid(&input), expected);
+        assert_eq!(brute_force(&input), expected);
+    }
+
+    #[test]
+    fn ut_closest_pair_degenerate() {
+        assert_eq!(closest_pair(&[]), None);
+        assert_eq!(closest_pair(&[Point::from((1.0, 1.0))]), None);
+        assert_eq!(closest_pair_grid(&[Point::from((1.0, 1.0))]), None);
+
+        // duplicate points are at distance zero
+        let input = vec![
+            Point::from((0.0, 0.0)),
+            Point::from((4.0, 4.0)),
+            Point::from((9.0, 1.0)),
+            Point::from((4.0, 4.0)),
+        ];
+        assert_eq!(closest_pair(&input), Some((1, 3, 0.0)));
+        assert_eq!(closest_pair_grid(&input), Some((1, 3, 0.0)));
+
+        // collinear points along a vertical line
+        let input: Vec<Point<f32>> = (0..50)
+            .map(|i| Point::from((3.0, (i * i) as f32)))
+            .collect();
+        check_against_brute_force(&input);
+    }
+
+    #[test]
+    fn ut_closest_pair_random() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for size in [2, 3, 4, 10, 100, 1_000] {
+            let input: Vec<Point<f32>> = (0..size)
+                .map(|_| Point::from((rng.gen::<f32>() * 512.0, rng.gen::<f32>() * 512.0)))
+                .collect();
+            check_against_brute_force(&input);
+        }
+        // many duplicates and ties on a coarse grid
+        for size in [10, 50, 200] {
+            let input: Vec<Point<f32>> = (0..size)
+                .map(|_| Point::from((rng.gen_range(0..40) as f32, rng.gen_range(0..40) as f32)))
+                .collect();
+            check_against_brute_force(&input);
+        }
+    }
+}
@@ -0,0 +1,906 @@
+/// Project: euclid
+/// Module: quadtree
+///
+/// This file contains functions to construct and query point quadtrees and
+/// point-region (PR) quadtrees.
+use crate::primitives::*;
+use crate::query_shape::*;
+use std::cmp::Ordering;
+
+/// Index of a node within the tree's arena of nodes.
+type Child = Option<usize>;
+
+/// The directions towards the neighbours of a cell (west, east, south, and
+/// north) along with the quadrants of each neighbour that touch the cell.
+const NEIGHBOURS: [((f32, f32), [usize; 2]); 4] = [
+    ((-1.0, 0.0), [1, 3]),
+    ((1.0, 0.0), [0, 2]),
+    ((0.0, -1.0), [2, 3]),
+    ((0.0, 1.0), [0, 1]),
+];
+
+/// Splits the box `cell` at the point `(x, y)` into its four quadrants, in the
+/// order south-west, south-east, north-west, and north-east.
+fn quadrants(cell: &Bounds<2>, x: f32, y: f32) -> [Bounds<2>; 4] {
+    let (west, east) = cell.split(0, x);
+    let (sw, nw) = west.split(1, y);
+    let (se, ne) = east.split(1, y);
+    [sw, se, nw, ne]
+}
+
+/// Returns the index of the quadrant around `(x, y)` containing the point `p`
+/// (points on a split-line belong to the east or north side).
+fn quadrant(p: &Point<f32>, x: f32, y: f32) -> usize {
+    (p.x() >= x) as usize + 2 * (p.y() >= y) as usize
+}
+
+/// Returns the index of the quadrant around the point `data` of a point
+/// quadtree node that holds the point `p`, which is east of `data` if it
+/// follows `data` in the composite-number order of `compare_from` (so points
+/// on the vertical split-line are split between both sides).
+fn point_quadrant(p: &Point<f32>, data: &Point<f32>) -> usize {
+    (compare_from(p, data, 0) == Ordering::Greater) as usize + 2 * (p.y() >= data.y()) as usize
+}
+
+/// Computes the distance from the point `p` to the closest point of `cell`.
+fn cell_dist(cell: &Bounds<2>, p: &Point<f32>) -> f32 {
+    let dx = p.x() - p.x().clamp(cell.min(0), cell.max(0));
+    let dy = p.y() - p.y().clamp(cell.min(1), cell.max(1));
+    f32::sqrt(dx * dx + dy * dy)
+}
+
+#[derive(Debug, PartialEq)]
+struct PointNode<V> {
+    data: Point<f32>,
+    // the values of every copy of the point
+    values: Vec<V>,
+    // the subtrees of the quadrants around the point (SW, SE, NW, NE)
+    children: [Child; 4],
+}
+
+/// A point quadtree over points in the plane.
+///
+/// Each node stores a point along with all of its copies and splits the other
+/// points of its subtree into the four quadrants around it. Constructing the
+/// tree at once picks the median point along the x-axis (in the
+/// composite-number order of `compare_from`) for every node. The points before
+/// it in that order go to the western quadrants and the points after it to the
+/// eastern ones, even if they lie on the split-line, so no quadrant receives
+/// more than half of the points and the depth is O(log(n)). Later insertions
+/// descend to an empty quadrant (or a copy of the point) without rebalancing.
+#[derive(Debug, PartialEq)]
+pub struct PointQuadtree<V = ()> {
+    nodes: Vec<PointNode<V>>,
+    root: Child,
+    len: usize,
+}
+
+impl PointQuadtree {
+    /// Builds a point quadtree for the set of known points `p`.
+    pub fn construct(points: Vec<Point<f32>>) -> Self {
+        Self::construct_items(points.into_iter().map(|p| (p, ())).collect())
+    }
+
+    /// Inserts the point `p` into the tree.
+    pub fn insert(&mut self, p: Point<f32>) {
+        self.insert_item(p, ())
+    }
+}
+
+impl<V> PointQuadtree<V> {
+    /// Builds a point quadtree for the set of known points holding values
+    /// `items`.
+    ///
+    /// - Time complexity: O(n log(n))
+    pub fn construct_items(mut items: Vec<(Point<f32>, V)>) -> Self {
+        items.sort_by(|a, b| compare_from(&a.0, &b.0, 0));
+        let len = items.len();
+        let mut nodes = Vec::with_capacity(items.len());
+        let root = Self::build(&mut nodes, items);
+        Self { nodes, root, len }
+    }
+
+    /// Recursive function call to build the subtree over `items` (assumes
+    /// points are already sorted by x-coordinate).
+    fn build(nodes: &mut Vec<PointNode<V>>, mut items: Vec<(Point<f32>, V)>) -> Child {
+        if items.is_empty() {
+            return None;
+        }
+        // the median along with its copies, which are next to it
+        let data = items[items.len() / 2].0;
+        let lo = items.partition_point(|(p, _)| compare_from(p, &data, 0).is_lt());
+        let hi = items.partition_point(|(p, _)| compare_from(p, &data, 0).is_le());
+        let rest = items.split_off(hi);
+        let copies = items.split_off(lo);
+
+        // partition the other points by quadrant (keeping them sorted)
+        let mut parts: [Vec<(Point<f32>, V)>; 4] = Default::default();
+        for item in items.into_iter().chain(rest) {
+            parts[point_quadrant(&item.0, &data)].push(item);
+        }
+
+        let id = nodes.len();
+        nodes.push(PointNode {
+            data,
+            values: copies.into_iter().map(|(_, value)| value).collect(),
+            children: [None; 4],
+        });
+        for (i, part) in parts.into_iter().enumerate() {
+            nodes[id].children[i] = Self::build(nodes, part);
+        }
+        Some(id)
+    }
+
+    /// Inserts the point `p` holding `value` into the tree.
+    ///
+    /// - Time complexity: O(depth)
+    pub fn insert_item(&mut self, p: Point<f32>, value: V) {
+        self.len += 1;
+        let mut cur = self.root;
+        let mut link = None;
+        while let Some(id) = cur {
+            let data = self.nodes[id].data;
+            if data == p {
+                self.nodes[id].values.push(value);
+                return;
+            }
+            let q = point_quadrant(&p, &data);
+            (cur, link) = (self.nodes[id].children[q], Some((id, q)));
+        }
+        let id = self.nodes.len();
+        self.nodes.push(PointNode {
+            data: p,
+            values: vec![value],
+            children: [None; 4],
+        });
+        match link {
+            Some((parent, q)) => self.nodes[parent].children[q] = Some(id),
+            None => self.root = Some(id),
+        }
+    }
+
+    /// Returns the number of points stored in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the tree stores no points.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Asks a range query question for the points contained within `shape`
+    /// (such as a `Region`).
+    pub fn range_query<S: QueryShape<2>>(&self, shape: &S) -> Vec<&Point<f32>> {
+        let mut result = Vec::new();
+        if let Some(root) = self.root {
+            self.search_tree(shape, root, Bounds::everything(), &mut |n| {
+                result.extend(n.values.iter().map(|_| &n.data))
+            });
+        }
+        result
+    }
+
+    /// Asks a range query question for the points contained within `shape`
+    /// to return the points along with their values.
+    pub fn range_query_items<S: QueryShape<2>>(&self, shape: &S) -> Vec<(&Point<f32>, &V)> {
+        let mut result = Vec::new();
+        if let Some(root) = self.root {
+            self.search_tree(shape, root, Bounds::everything(), &mut |n| {
+                result.extend(n.values.iter().map(|v| (&n.data, v)))
+            });
+        }
+        result
+    }
+
+    /// Recursive function call that traverses down the (sub)tree from `node`,
+    /// whose points lie within `cell`, to report the nodes whose points are
+    /// contained within `shape`.
+    fn search_tree<'a, S: QueryShape<2>>(
+        &'a self,
+        shape: &S,
+        node: usize,
+        cell: Bounds<2>,
+        report: &mut impl FnMut(&'a PointNode<V>),
+    ) {
+        match shape.classify(&cell) {
+            Overlap::Outside => (),
+            Overlap::Inside => {
+                let mut stack = vec![node];
+                while let Some(id) = stack.pop() {
+                    report(&self.nodes[id]);
+                    stack.extend(self.nodes[id].children.into_iter().flatten());
+                }
+            }
+            Overlap::Crossing => {
+                let n = &self.nodes[node];
+                if shape.contains(&n.data) {
+                    report(n);
+                }
+                let cells = quadrants(&cell, n.data.x(), n.data.y());
+                for (child, cell) in n.children.into_iter().zip(cells) {
+                    if let Some(child) = child {
+                        self.search_tree(shape, child, cell, report);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the closest point in the tree to the point `p`.
+    pub fn nearest(&self, p: &Point<f32>) -> Option<&Point<f32>> {
+        self.nearest_item(p).map(|(q, _)| q)
+    }
+
+    /// Finds the closest point in the tree to the point `p` along with its
+    /// value.
+    pub fn nearest_item(&self, p: &Point<f32>) -> Option<(&Point<f32>, &V)> {
+        let mut best = None;
+        if let Some(root) = self.root {
+            self.search_nearest(p, root, Bounds::everything(), &mut best);
+        }
+        best.map(|(_, n)| (&n.data, &n.values[0]))
+    }
+
+    /// Recursive function call that traverses down the (sub)tree from `node`,
+    /// whose points lie within `cell`, to find the closest point to `p`,
+    /// visiting the quadrants closest to `p` first.
+    fn search_nearest<'a>(
+        &'a self,
+        p: &Point<f32>,
+        node: usize,
+        cell: Bounds<2>,
+        best: &mut Option<(f32, &'a PointNode<V>)>,
+    ) {
+        let n = &self.nodes[node];
+        let dist = euclid_dist(p, &n.data);
+        if best.is_none_or(|(d, _)| dist < d) {
+            *best = Some((dist, n));
+        }
+        let cells = quadrants(&cell, n.data.x(), n.data.y());
+        let mut order = [0, 1, 2, 3].map(|q| (cell_dist(&cells[q], p), q));
+        order.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        for (bound, q) in order {
+            if let (Some(child), true) = (n.children[q], best.is_none_or(|(d, _)| bound < d)) {
+                self.search_nearest(p, child, cells[q], best);
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Node<V> {
+    cell: Bounds<2>,
+    depth: usize,
+    // the four quadrants (SW, SE, NW, NE) of an internal node
+    children: Option<[usize; 4]>,
+    // the points within the cell of a leaf along with their values
+    items: Vec<(Point<f32>, V)>,
+    // the number of points within the subtree
+    count: usize,
+}
+
+impl<V> Node<V> {
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_none()
+    }
+
+    pub fn new(cell: Bounds<2>, depth: usize) -> Self {
+        Self {
+            cell,
+            depth,
+            children: None,
+            items: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// Returns the center of the cell, where its quadrants meet.
+    fn middle(&self) -> (f32, f32) {
+        (
+            (self.cell.min(0) + self.cell.max(0)) / 2.0,
+            (self.cell.min(1) + self.cell.max(1)) / 2.0,
+        )
+    }
+}
+
+/// A point-region (PR) quadtree over points in the plane.
+///
+/// The root covers a fixed rectangle, and every internal node splits its cell
+/// at the center into four equal quadrants. A leaf holds up to `capacity`
+/// points (its bucket) before it is split, unless it is at `max_depth`, where
+/// it holds any number of points so that duplicate or clustered points cannot
+/// split cells forever. The shape of the tree only depends on the set of
+/// points, not on their order of insertion.
+#[derive(Debug, PartialEq)]
+pub struct PrQuadtree<V = ()> {
+    nodes: Vec<Node<V>>,
+    root: usize,
+    // slots in the arena of nodes that were merged away
+    free: Vec<usize>,
+    capacity: usize,
+    max_depth: usize,
+}
+
+impl PrQuadtree {
+    /// Builds a PR quadtree over the bounding square of the set of known
+    /// points `p`, holding up to `capacity` points per leaf and splitting
+    /// cells down to `max_depth`.
+    pub fn construct(points: Vec<Point<f32>>, capacity: usize, max_depth: usize) -> Self {
+        Self::construct_items(
+            points.into_iter().map(|p| (p, ())).collect(),
+            capacity,
+            max_depth,
+        )
+    }
+
+    /// Inserts the point `p` into the tree (if it is within the root's cell).
+    pub fn insert(&mut self, p: Point<f32>) -> bool {
+        self.insert_item(p, ())
+    }
+}
+
+impl<V> PrQuadtree<V> {
+    /// Creates an empty PR quadtree whose root covers `bounds`, holding up to
+    /// `capacity` points per leaf and splitting cells down to `max_depth`.
+    pub fn new(bounds: Region<f32>, capacity: usize, max_depth: usize) -> Self {
+        assert!(capacity > 0, "a leaf must hold at least 1 point");
+        Self {
+            nodes: vec![Node::new(Bounds::from(bounds), 0)],
+            root: 0,
+            free: Vec::new(),
+            capacity,
+            max_depth,
+        }
+    }
+
+    /// Builds a PR quadtree over the bounding square of the set of known
+    /// points holding values `items`.
+    ///
+    /// - Time complexity: O(n * depth)
+    pub fn construct_items(items: Vec<(Point<f32>, V)>, capacity: usize, max_depth: usize) -> Self {
+        let (mut min, mut max) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+        for (p, _) in &items {
+            min = [min[0].min(p.x()), min[1].min(p.y())];
+            max = [max[0].max(p.x()), max[1].max(p.y())];
+        }
+        if items.is_empty() {
+            (min, max) = ([0.0; 2], [0.0; 2]);
+        }
+        // the far corner may round below the highest coordinates otherwise
+        let side = (max[0] - min[0]).max(max[1] - min[1]);
+        let bounds = Region::new(
+            Point::from((min[0], min[1])),
+            Point::from(((min[0] + side).max(max[0]), (min[1] + side).max(max[1]))),
+        );
+
+        let mut tree = Self::new(bounds, capacity, max_depth);
+        for (p, value) in items {
+            tree.insert_item(p, value);
+        }
+        tree
+    }
+
+    /// Stores `node` in the arena, reusing a free slot if possible.
+    fn alloc(&mut self, node: Node<V>) -> usize {
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = node;
+                id
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Returns the number of points stored in the tree.
+    pub fn len(&self) -> usize {
+        self.nodes[self.root].count
+    }
+
+    /// Checks if the tree stores no points.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts the point `p` holding `value` into the tree, splitting its leaf
+    /// once it holds more than `capacity` points.
+    ///
+    /// Returns false without inserting `p` if it is outside of the root's cell.
+    ///
+    /// - Time complexity: O(depth + capacity)
+    pub fn insert_item(&mut self, p: Point<f32>, value: V) -> bool {
+        if !self.nodes[self.root].cell.contains_point(&p) {
+            return false;
+        }
+        let mut cur = self.root;
+        loop {
+            self.nodes[cur].count += 1;
+            match self.nodes[cur].children {
+                Some(children) => {
+                    let (x, y) = self.nodes[cur].middle();
+                    cur = children[quadrant(&p, x, y)];
+                }
+                None => break,
+            }
+        }
+        self.nodes[cur].items.push((p, value));
+        if self.nodes[cur].items.len() > self.capacity && self.nodes[cur].depth < self.max_depth {
+            self.split(cur);
+        }
+        true
+    }
+
+    /// Splits the leaf `id` into four quadrants and hands out its points,
+    /// which splits the quadrants that are still too full in turn.
+    fn split(&mut self, id: usize) {
+        let (x, y) = self.nodes[id].middle();
+        let depth = self.nodes[id].depth + 1;
+        let cells = quadrants(&self.nodes[id].cell, x, y);
+        let children = cells.map(|cell| self.alloc(Node::new(cell, depth)));
+        for item in std::mem::take(&mut self.nodes[id].items) {
+            let child = &mut self.nodes[children[quadrant(&item.0, x, y)]];
+            child.items.push(item);
+            child.count += 1;
+        }
+        self.nodes[id].children = Some(children);
+        for child in children {
+            if self.nodes[child].items.len() > self.capacity && depth < self.max_depth {
+                self.split(child);
+            }
+        }
+    }
+
+    /// Removes an instance of the point `p` from the tree to return its value
+    /// (if it exists).
+    ///
+    /// The highest subtree along the way that holds no more than `capacity`
+    /// points afterwards is merged back into a single leaf.
+    ///
+    /// - Time complexity: O(depth + capacity)
+    pub fn remove(&mut self, p: &Point<f32>) -> Option<V> {
+        if !self.nodes[self.root].cell.contains_point(p) {
+            return None;
+        }
+        let mut path = vec![self.root];
+        while let Some(children) = self.nodes[*path.last().unwrap()].children {
+            let (x, y) = self.nodes[*path.last().unwrap()].middle();
+            path.push(children[quadrant(p, x, y)]);
+        }
+        let leaf = &mut self.nodes[*path.last().unwrap()];
+        let i = leaf.items.iter().position(|(q, _)| q == p)?;
+        let (_, value) = leaf.items.swap_remove(i);
+
+        for &id in &path {
+            self.nodes[id].count -= 1;
+        }
+        if let Some(&id) = path
+            .iter()
+            .find(|&&id| !self.nodes[id].is_leaf() && self.nodes[id].count <= self.capacity)
+        {
+            self.merge(id);
+        }
+        Some(value)
+    }
+
+    /// Collects the points below the internal node `id` into it to make it a
+    /// leaf, freeing the nodes of its former subtree.
+    fn merge(&mut self, id: usize) {
+        let mut items = Vec::with_capacity(self.nodes[id].count);
+        let mut stack: Vec<usize> = self.nodes[id]
+            .children
+            .take()
+            .into_iter()
+            .flatten()
+            .collect();
+        while let Some(child) = stack.pop() {
+            items.append(&mut self.nodes[child].items);
+            stack.extend(self.nodes[child].children.take().into_iter().flatten());
+            self.free.push(child);
+        }
+        self.nodes[id].items = items;
+    }
+
+    /// Asks a range query question for the points contained within `shape`
+    /// (such as a `Region`).
+    pub fn range_query<S: QueryShape<2>>(&self, shape: &S) -> Vec<&Point<f32>> {
+        let mut result = Vec::new();
+        self.search(shape, &mut |(p, _)| result.push(p));
+        result
+    }
+
+    /// Asks a range query question for the points contained within `shape`
+    /// to return the points along with their values.
+    pub fn range_query_items<S: QueryShape<2>>(&self, shape: &S) -> Vec<(&Point<f32>, &V)> {
+        let mut result = Vec::new();
+        self.search(shape, &mut |(p, v)| result.push((p, v)));
+        result
+    }
+
+    /// Walks down the tree with an explicit stack to report the items whose
+    /// points are contained within `shape`, skipping the containment test
+    /// below cells that are entirely inside.
+    fn search<'a, S: QueryShape<2>>(
+        &'a self,
+        shape: &S,
+        report: &mut impl FnMut(&'a (Point<f32>, V)),
+    ) {
+        let mut stack = vec![(self.root, false)];
+        while let Some((id, mut inside)) = stack.pop() {
+            let node = &self.nodes[id];
+            if !inside {
+                match shape.classify(&node.cell) {
+                    Overlap::Outside => continue,
+                    Overlap::Inside => inside = true,
+                    Overlap::Crossing => (),
+                }
+            }
+            match node.children {
+                Some(children) => stack.extend(children.map(|c| (c, inside))),
+                None => {
+                    for item in &node.items {
+                        if inside || shape.contains(&item.0) {
+                            report(item);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the closest point in the tree to the point `p`.
+    pub fn nearest(&self, p: &Point<f32>) -> Option<&Point<f32>> {
+        self.nearest_item(p).map(|(q, _)| q)
+    }
+
+    /// Finds the closest point in the tree to the point `p` along with its
+    /// value.
+    pub fn nearest_item(&self, p: &Point<f32>) -> Option<(&Point<f32>, &V)> {
+        let mut best = None;
+        self.search_nearest(p, self.root, &mut best);
+        best.map(|(_, (q, v))| (q, v))
+    }
+
+    /// Recursive function call that traverses down the (sub)tree from `node`
+    /// to find the closest point to `p`, visiting the quadrants closest to `p`
+    /// first and skipping those farther away than the best point so far.
+    fn search_nearest<'a>(
+        &'a self,
+        p: &Point<f32>,
+        node: usize,
+        best: &mut Option<(f32, &'a (Point<f32>, V))>,
+    ) {
+        match self.nodes[node].children {
+            None => {
+                for item in &self.nodes[node].items {
+                    let dist = euclid_dist(p, &item.0);
+                    if best.is_none_or(|(d, _)| dist < d) {
+                        *best = Some((dist, item));
+                    }
+                }
+            }
+            Some(children) => {
+                let mut children = children.map(|c| (cell_dist(&self.nodes[c].cell, p), c));
+                children.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+                for (bound, child) in children {
+                    if self.nodes[child].count > 0 && best.is_none_or(|(d, _)| bound < d) {
+                        self.search_nearest(p, child, best);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Refines the tree until it is balanced: the cells of any two leaves that
+    /// share an edge differ in size by at most a factor of two.
+    ///
+    /// A leaf must be split while one of the quadrants of its equal-sized
+    /// neighbour that touch it is split further. Splitting a leaf may in turn
+    /// require its larger neighbours to be split, so those are checked again.
+    /// Later insertions and removals do not keep the tree balanced.
+    ///
+    /// - Time complexity: O(m * depth), for m nodes
+    pub fn balance(&mut self) {
+        let mut queue = self.leaves();
+        while let Some(id) = queue.pop() {
+            if !self.nodes[id].is_leaf() || !self.must_split(id) {
+                continue;
+            }
+            self.split(id);
+            queue.extend(self.nodes[id].children.unwrap());
+            for (dir, _) in NEIGHBOURS {
+                if let Some(n) = self.neighbour(id, dir) {
+                    if self.nodes[n].is_leaf() && self.nodes[n].depth < self.nodes[id].depth {
+                        queue.push(n);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the leaves of the tree.
+    fn leaves(&self) -> Vec<usize> {
+        let mut leaves = Vec::new();
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            match self.nodes[id].children {
+                Some(children) => stack.extend(children),
+                None => leaves.push(id),
+            }
+        }
+        leaves
+    }
+
+    /// Finds the node of the same depth as `id` next to it in the direction
+    /// `dir`, or the leaf containing that node's cell (if within the tree).
+    fn neighbour(&self, id: usize, dir: (f32, f32)) -> Option<usize> {
+        let node = &self.nodes[id];
+        let (x, y) = node.middle();
+        let q = Point::from((
+            x + dir.0 * (node.cell.max(0) - node.cell.min(0)),
+            y + dir.1 * (node.cell.max(1) - node.cell.min(1)),
+        ));
+        if !self.nodes[self.root].cell.contains_point(&q) {
+            return None;
+        }
+        let mut cur = self.root;
+        while let (Some(children), true) =
+            (self.nodes[cur].children, self.nodes[cur].depth < node.depth)
+        {
+            let (x, y) = self.nodes[cur].middle();
+            cur = children[quadrant(&q, x, y)];
+        }
+        Some(cur)
+    }
+
+    /// Checks if the leaf `id` touches a leaf that is less than half its size.
+    fn must_split(&self, id: usize) -> bool {
+        NEIGHBOURS.iter().any(|&(dir, near)| {
+            self.neighbour(id, dir).is_some_and(|n| {
+                match (
+                    self.nodes[n].depth == self.nodes[id].depth,
+                    self.nodes[n].children,
+                ) {
+                    (true, Some(children)) => {
+                        near.iter().any(|&q| !self.nodes[children[q]].is_leaf())
+                    }
+                    _ => false,
+                }
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Sorts the points by increasing x-coordinate, and then increasing y-coordinate.
+    fn sorted(mut points: Vec<&Point<f32>>) -> Vec<&Point<f32>> {
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        points
+    }
+
+    /// Samples points clustered around a few centers, with some duplicates.
+    fn sample_points(rng: &mut impl Rng, size: usize) -> Vec<Point<f32>> {
+        let centers: Vec<(f32, f32)> = (0..4)
+            .map(|_| (rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0))
+            .collect();
+        let mut points: Vec<Point<f32>> = (0..size)
+            .map(|_| {
+                let (x, y) = centers[rng.gen_range(0..centers.len())];
+                let spread = rng.gen::<f32>().powi(3) * 10.0;
+                Point::from((
+                    (x + (rng.gen::<f32>() - 0.5) * spread).clamp(0.0, 100.0),
+                    (y + (rng.gen::<f32>() - 0.5) * spread).clamp(0.0, 100.0),
+                ))
+            })
+            .collect();
+        for i in 0..size / 10 {
+            points.push(points[i]);
+        }
+        points
+    }
+
+    /// Finds the distance from `p` to the closest point of `points`.
+    fn nearest_dist(points: &[Point<f32>], p: &Point<f32>) -> f32 {
+        points
+            .iter()
+            .map(|q| euclid_dist(p, q))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Returns the number of nodes on the longest path from the root.
+    fn depth<V>(tree: &PointQuadtree<V>) -> usize {
+        let mut depth = 0;
+        let mut stack: Vec<(usize, usize)> = tree.root.map(|r| (r, 1)).into_iter().collect();
+        while let Some((id, d)) = stack.pop() {
+            depth = depth.max(d);
+            stack.extend(
+                tree.nodes[id]
+                    .children
+                    .into_iter()
+                    .flatten()
+                    .map(|c| (c, d + 1)),
+            );
+        }
+        depth
+    }
+
+    #[test]
+    fn ut_point_quadtree_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let points = sample_points(&mut rng, 500);
+        let (first, rest) = points.split_at(250);
+        let mut tree = PointQuadtree::construct(first.to_vec());
+        for p in rest {
+            tree.insert(*p);
+        }
+        assert_eq!(tree.len(), points.len());
+
+        for _ in 0..50 {
+            let region = sample_region(&mut rng, 0.0..100.0);
+            let expected = points.iter().filter(|p| region.contains_point(p)).collect();
+            assert_eq!(sorted(tree.range_query(&region)), sorted(expected));
+
+            let p = sample_point(&mut rng, 0.0..120.0);
+            let q = tree.nearest(&p).unwrap();
+            assert_eq!(euclid_dist(&p, q), nearest_dist(&points, &p));
+        }
+        assert_eq!(
+            PointQuadtree::construct(Vec::new()).nearest(&Point::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn ut_point_quadtree_duplicates() {
+        let p = Point::from((1.0, 1.0));
+        let mut tree = PointQuadtree::construct(vec![p; 200000]);
+        for _ in 0..1000 {
+            tree.insert(p);
+        }
+        // the copies share a single node
+        assert_eq!(tree.nodes.len(), 1);
+        assert_eq!(tree.len(), 201000);
+        assert_eq!(tree.range_query(&Region::new(p, p)).len(), 201000);
+        assert_eq!(tree.nearest(&Point::new()), Some(&p));
+    }
+
+    #[test]
+    fn ut_point_quadtree_degenerate() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for points in degenerate_points(&mut rng, 200) {
+            let tree = PointQuadtree::construct(points.clone());
+            // points on the split-lines are divided between both sides
+            assert!(depth(&tree) <= 8);
+            for region in grid_regions() {
+                let expected = points.iter().filter(|p| region.contains_point(p)).collect();
+                assert_eq!(sorted(tree.range_query(&region)), sorted(expected));
+            }
+        }
+        // points on a vertical line with few duplicates
+        let points: Vec<Point<f32>> = (0..1 << 12).map(|i| Point::from((0.0, i as f32))).collect();
+        assert!(depth(&PointQuadtree::construct(points)) <= 13);
+    }
+
+    #[test]
+    fn ut_pr_quadtree_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut points = sample_points(&mut rng, 500);
+        // leave room to insert points around the samples
+        let mut tree = PrQuadtree::new(
+            Region::new(Point::from((0.0, 0.0)), Point::from((100.0, 100.0))),
+            4,
+            12,
+        );
+        for p in &points {
+            tree.insert(*p);
+        }
+        assert_eq!(tree.len(), points.len());
+
+        for _ in 0..50 {
+            // remove a few points to merge cells back together
+            for _ in 0..5 {
+                let p = points.swap_remove(rng.gen_range(0..points.len()));
+                assert_eq!(tree.remove(&p), Some(()));
+            }
+            let p = sample_point(&mut rng, 0.0..100.0);
+            tree.insert(p);
+            points.push(p);
+            assert_eq!(tree.len(), points.len());
+
+            let region = sample_region(&mut rng, 0.0..100.0);
+            let expected = points.iter().filter(|p| region.contains_point(p)).collect();
+            assert_eq!(sorted(tree.range_query(&region)), sorted(expected));
+
+            let q = tree.nearest(&p).unwrap();
+            assert_eq!(euclid_dist(&p, q), 0.0);
+            let p = sample_point(&mut rng, 0.0..120.0);
+            let q = tree.nearest(&p).unwrap();
+            assert_eq!(euclid_dist(&p, q), nearest_dist(&points, &p));
+        }
+        assert_eq!(tree.remove(&Point::from((-1.0, -1.0))), None);
+        // points outside of the root's cell are not inserted
+        assert!(!tree.insert(Point::from((-1.0, -1.0))));
+        assert!(!tree.insert(Point::from((50.0, 100.5))));
+        assert_eq!(tree.len(), points.len());
+    }
+
+    #[test]
+    fn ut_pr_quadtree_bounds() {
+        // the bounding square of these points rounds below the top corner
+        let points = vec![Point::from((-43.3, 0.0)), Point::from((42.4, 1.0))];
+        let tree = PrQuadtree::construct(points.clone(), 1, 4);
+        assert_eq!(
+            sorted(tree.range_query(&Bounds::everything())),
+            sorted(points.iter().collect())
+        );
+    }
+
+    #[test]
+    fn ut_pr_quadtree_capacity() {
+        let mut tree = PrQuadtree::<&str>::new(
+            Region::new(Point::from((0.0, 0.0)), Point::from((8.0, 8.0))),
+            2,
+            3,
+        );
+        for (i, name) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            tree.insert_item(Point::from((1.0, 1.0)), name);
+            // duplicates stop splitting at the maximum depth
+            assert_eq!(tree.leaves().len(), 1 + 3 * 3 * (i >= 2) as usize);
+        }
+        tree.insert_item(Point::from((7.0, 7.0)), "e");
+        assert_eq!(tree.nearest_item(&Point::from((5.0, 6.0))).unwrap().1, &"e");
+        assert_eq!(
+            tree.range_query_items(&Region::new(
+                Point::from((0.0, 0.0)),
+                Point::from((1.0, 1.0))
+            ))
+            .len(),
+            4
+        );
+
+        for _ in 0..3 {
+            tree.remove(&Point::from((1.0, 1.0)));
+        }
+        // the 2 remaining points fit within the root
+        assert_eq!(tree.leaves(), vec![tree.root]);
+    }
+
+    #[test]
+    fn ut_balance() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let points = sample_points(&mut rng, 500);
+        let mut tree = PrQuadtree::construct(points.clone(), 1, 10);
+        tree.balance();
+
+        let leaves: Vec<&Node<()>> = tree.leaves().iter().map(|&id| &tree.nodes[id]).collect();
+        for a in &leaves {
+            for b in &leaves {
+                // the cells share a part of an edge
+                let (p, q) = (&a.cell, &b.cell);
+                let touching = (0..2).any(|axis| {
+                    let other = 1 - axis;
+                    p.max(axis) == q.min(axis)
+                        && p.min(other) < q.max(other)
+                        && q.min(other) < p.max(other)
+                });
+                // the side of a cell halves with every level of depth
+                if touching {
+                    assert!(a.depth.abs_diff(b.depth) <= 1);
+                }
+            }
+        }
+
+        // balancing only refines the cells
+        assert_eq!(tree.len(), points.len());
+        let region = sample_region(&mut rng, 0.0..100.0);
+        let expected = points.iter().filter(|p| region.contains_point(p)).collect();
+        assert_eq!(sorted(tree.range_query(&region)), sorted(expected));
+    }
+}
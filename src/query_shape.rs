@@ -0,0 +1,292 @@
+/// Project: euclid
+/// Module: query_shape
+///
+/// This file contains the shapes of the ranges that a kd-tree can be queried
+/// with, beyond axis-aligned boxes.
+use crate::primitives::*;
+
+/// How a cell of space relates to the range of a query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overlap {
+    /// Every point of the cell is within the range.
+    Inside,
+    /// No point of the cell is within the range.
+    Outside,
+    /// The cell may contain points both within and outside the range.
+    Crossing,
+}
+
+/// A range in `D`-dimensional space that can be searched for the points it
+/// contains.
+pub trait QueryShape<const D: usize> {
+    /// Checks if a point `p` is within the range (including the boundary).
+    fn contains<P: Coordinates<D>>(&self, p: &P) -> bool;
+
+    /// Classifies how the box `cell` overlaps the range.
+    ///
+    /// The cell's bounds may be infinite. Returning `Crossing` is always
+    /// correct, but costs the search more time.
+    fn classify(&self, cell: &Bounds<D>) -> Overlap;
+}
+
+impl<const D: usize> QueryShape<D> for Bounds<D> {
+    fn contains<P: Coordinates<D>>(&self, p: &P) -> bool {
+        self.contains_point(p)
+    }
+
+    fn classify(&self, cell: &Bounds<D>) -> Overlap {
+        if self.contains_bounds(cell) {
+            Overlap::Inside
+        } else if self.intersects(cell) {
+            Overlap::Crossing
+        } else {
+            Overlap::Outside
+        }
+    }
+}
+
+impl QueryShape<2> for Region<f32> {
+    fn contains<P: Coordinates<2>>(&self, p: &P) -> bool {
+        Bounds::from(*self).contains_point(p)
+    }
+
+    fn classify(&self, cell: &Bounds<2>) -> Overlap {
+        Bounds::from(*self).classify(cell)
+    }
+}
+
+impl QueryShape<2> for Circle {
+    fn contains<P: Coordinates<2>>(&self, p: &P) -> bool {
+        self.contains_point(&Point::from((p.coord(0), p.coord(1))))
+    }
+
+    fn classify(&self, cell: &Bounds<2>) -> Overlap {
+        let c = self.center();
+        // the closest and farthest points of the cell from the center
+        let (mut near, mut far) = ([0.0; 2], [0.0; 2]);
+        for (axis, x) in [c.x(), c.y()].into_iter().enumerate() {
+            let (lo, hi) = (cell.min(axis), cell.max(axis));
+            near[axis] = x.clamp(lo, hi);
+            far[axis] = match x - lo >= hi - x {
+                true => lo,
+                false => hi,
+            };
+        }
+        if euclid_dist(c, &Point::from((far[0], far[1]))) <= self.radius() {
+            Overlap::Inside
+        } else if euclid_dist(c, &Point::from((near[0], near[1]))) > self.radius() {
+            Overlap::Outside
+        } else {
+            Overlap::Crossing
+        }
+    }
+}
+
+/// A closed halfplane containing the points `x` for which `normal · x <= offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HalfPlane {
+    normal: [f32; 2],
+    offset: f32,
+}
+
+impl HalfPlane {
+    /// Creates the halfplane on the left of (or on) the directed line passing
+    /// through `p` and then `q`.
+    pub fn new(p: &Point<f32>, q: &Point<f32>) -> Self {
+        let normal = [q.y() - p.y(), p.x() - q.x()];
+        Self {
+            normal,
+            offset: normal[0] * p.x() + normal[1] * p.y(),
+        }
+    }
+
+    /// Computes the lowest and highest values of `normal · x` over all points
+    /// `x` within the box `cell`.
+    fn extent(&self, cell: &Bounds<2>) -> (f32, f32) {
+        let (mut low, mut high) = (0.0, 0.0);
+        for (axis, n) in self.normal.into_iter().enumerate() {
+            // skip the axis parallel to the boundary to avoid 0 * inf
+            if n > 0.0 {
+                low += n * cell.min(axis);
+                high += n * cell.max(axis);
+            } else if n < 0.0 {
+                low += n * cell.max(axis);
+                high += n * cell.min(axis);
+            }
+        }
+        (low, high)
+    }
+}
+
+impl QueryShape<2> for HalfPlane {
+    fn contains<P: Coordinates<2>>(&self, p: &P) -> bool {
+        self.normal[0] * p.coord(0) + self.normal[1] * p.coord(1) <= self.offset
+    }
+
+    fn classify(&self, cell: &Bounds<2>) -> Overlap {
+        let (low, high) = self.extent(cell);
+        if high <= self.offset {
+            Overlap::Inside
+        } else if low > self.offset {
+            Overlap::Outside
+        } else {
+            Overlap::Crossing
+        }
+    }
+}
+
+/// A convex polygon, including its boundary, stored as the intersection of the
+/// halfplanes bounded by its edges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvexPolygon {
+    edges: Vec<HalfPlane>,
+    bounds: Bounds<2>,
+}
+
+impl ConvexPolygon {
+    /// Creates the polygon from its `vertices` listed in either clockwise or
+    /// counter-clockwise order.
+    ///
+    /// The vertices are assumed to be in convex position.
+    pub fn new(vertices: &[Point<f32>]) -> Self {
+        assert!(
+            vertices.len() >= 3,
+            "a polygon requires at least 3 vertices"
+        );
+        // twice the signed area is positive for counter-clockwise order
+        let area: f32 = (0..vertices.len())
+            .map(|i| {
+                let (p, q) = (&vertices[i], &vertices[(i + 1) % vertices.len()]);
+                p.x() * q.y() - q.x() * p.y()
+            })
+            .sum();
+        let edges = (0..vertices.len())
+            .map(|i| {
+                let (p, q) = (&vertices[i], &vertices[(i + 1) % vertices.len()]);
+                // the interior must be on the left side of each edge
+                match area < 0.0 {
+                    true => HalfPlane::new(q, p),
+                    false => HalfPlane::new(p, q),
+                }
+            })
+            .collect();
+        let bounds = vertices.iter().fold(
+            Bounds::new([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]),
+            |b, p| {
+                Bounds::new(
+                    [b.min(0).min(p.x()), b.min(1).min(p.y())],
+                    [b.max(0).max(p.x()), b.max(1).max(p.y())],
+                )
+            },
+        );
+        Self { edges, bounds }
+    }
+
+    /// Creates the triangle with corners `a`, `b`, and `c`.
+    pub fn triangle(a: Point<f32>, b: Point<f32>, c: Point<f32>) -> Self {
+        Self::new(&[a, b, c])
+    }
+}
+
+impl QueryShape<2> for ConvexPolygon {
+    fn contains<P: Coordinates<2>>(&self, p: &P) -> bool {
+        self.edges.iter().all(|h| h.contains(p))
+    }
+
+    /// Separates the cell from the polygon along the axes and the normals of
+    /// the edges, which is exact for two convex shapes.
+    fn classify(&self, cell: &Bounds<2>) -> Overlap {
+        if !self.bounds.intersects(cell) {
+            return Overlap::Outside;
+        }
+        let mut inside = true;
+        for h in &self.edges {
+            match h.classify(cell) {
+                Overlap::Outside => return Overlap::Outside,
+                Overlap::Crossing => inside = false,
+                Overlap::Inside => (),
+            }
+        }
+        match inside {
+            true => Overlap::Inside,
+            false => Overlap::Crossing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ut_halfplane() {
+        let h = HalfPlane::new(&Point::from((0.0, 0.0)), &Point::from((1.0, 1.0)));
+        assert!(h.contains(&Point::from((0.0, 5.0))));
+        assert!(h.contains(&Point::from((2.0, 2.0))));
+        assert!(!h.contains(&Point::from((5.0, 0.0))));
+
+        let above = Bounds::new([-10.0, 20.0], [10.0, 30.0]);
+        assert_eq!(h.classify(&above), Overlap::Inside);
+        let below = Bounds::new([20.0, f32::NEG_INFINITY], [f32::INFINITY, 10.0]);
+        assert_eq!(h.classify(&below), Overlap::Outside);
+        assert_eq!(h.classify(&Bounds::everything()), Overlap::Crossing);
+    }
+
+    #[test]
+    fn ut_convex_polygon() {
+        let corners = [
+            Point::from((0.0, 0.0)),
+            Point::from((4.0, 0.0)),
+            Point::from((4.0, 4.0)),
+            Point::from((0.0, 4.0)),
+        ];
+        // both orientations describe the same square
+        let mut reversed = corners;
+        reversed.reverse();
+        for square in [ConvexPolygon::new(&corners), ConvexPolygon::new(&reversed)] {
+            assert!(square.contains(&Point::from((2.0, 4.0))));
+            assert!(!square.contains(&Point::from((2.0, 5.0))));
+            assert_eq!(
+                square.classify(&Bounds::new([1.0, 1.0], [3.0, 3.0])),
+                Overlap::Inside
+            );
+            assert_eq!(
+                square.classify(&Bounds::new([3.0, 3.0], [5.0, 5.0])),
+                Overlap::Crossing
+            );
+            assert_eq!(
+                square.classify(&Bounds::new([5.0, f32::NEG_INFINITY], [6.0, f32::INFINITY])),
+                Overlap::Outside
+            );
+        }
+
+        // the cell is only separated from the triangle by its hypotenuse
+        let triangle = ConvexPolygon::triangle(
+            Point::from((0.0, 0.0)),
+            Point::from((4.0, 0.0)),
+            Point::from((0.0, 4.0)),
+        );
+        assert_eq!(
+            triangle.classify(&Bounds::new([3.0, 3.0], [4.0, 4.0])),
+            Overlap::Outside
+        );
+    }
+
+    #[test]
+    fn ut_disk() {
+        let disk = Circle::new(Point::from((0.0, 0.0)), 5.0);
+        assert_eq!(
+            disk.classify(&Bounds::new([-3.0, -3.0], [3.0, 3.0])),
+            Overlap::Inside
+        );
+        assert_eq!(
+            disk.classify(&Bounds::new([3.0, 3.0], [6.0, 6.0])),
+            Overlap::Crossing
+        );
+        assert_eq!(
+            disk.classify(&Bounds::new([4.0, 4.0], [6.0, 6.0])),
+            Overlap::Outside
+        );
+        assert_eq!(disk.classify(&Bounds::everything()), Overlap::Crossing);
+    }
+}
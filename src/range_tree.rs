@@ -3,21 +3,27 @@
 ///
 /// This file contains functions to construct and query a range-tree data
 /// structure.
+use crate::aggregate::*;
 use crate::primitives::*;
-use std::cmp::Ordering;
 
-type Child<T> = Option<Box<Node<T>>>;
+/// Index of a node within the tree's arena of nodes.
+type Child = Option<usize>;
 
 #[derive(Debug, PartialEq)]
-struct Node<T: Default + Copy> {
-    data: Point<T>,
-    left: Child<T>,
+struct Node<P, W> {
+    data: P,
+    // the index of the point's item (only used by leaves)
+    item: usize,
+    left: Child,
     // the associated structure (takes you to the next dimension!!)
-    assoc: Child<T>,
-    right: Child<T>,
+    assoc: Child,
+    right: Child,
+    // the number of points and their aggregate value within the subtree
+    count: usize,
+    agg: W,
 }
 
-impl<T: Default + Copy> Node<T> {
+impl<P, W> Node<P, W> {
     pub fn is_leaf(&self) -> bool {
         self.left.is_none() && self.right.is_none()
     }
@@ -26,278 +32,590 @@ impl<T: Default + Copy> Node<T> {
         self.assoc.is_none()
     }
 
-    pub fn next_dimension(&self) -> &Child<T> {
+    pub fn next_dimension(&self) -> &Child {
         &self.assoc
     }
 
-    pub fn new(data: Point<T>, left: Child<T>, right: Child<T>, assoc: Child<T>) -> Self {
+    pub fn new(
+        data: P,
+        item: usize,
+        left: Child,
+        right: Child,
+        assoc: Child,
+        count: usize,
+        agg: W,
+    ) -> Self {
         Self {
-            data: data,
-            left: left,
-            right: right,
-            assoc: assoc,
+            data,
+            item,
+            left,
+            right,
+            assoc,
+            count,
+            agg,
         }
     }
 }
 
+/// An iterator over the leaves of a set of subtrees, which walks down the
+/// subtrees with an explicit stack.
+struct SubtreeLeaves<'a, P, W> {
+    nodes: &'a [Node<P, W>],
+    stack: Vec<&'a Node<P, W>>,
+}
+
+impl<'a, P, W> Iterator for SubtreeLeaves<'a, P, W> {
+    type Item = &'a Node<P, W>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(n) = self.stack.pop() {
+            match n.is_leaf() {
+                true => return Some(n),
+                false => {
+                    if let Some(right) = n.right {
+                        self.stack.push(&self.nodes[right]);
+                    }
+                    if let Some(left) = n.left {
+                        self.stack.push(&self.nodes[left]);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// A range-tree over points in `D`-dimensional space.
+///
+/// The main tree is built on the first axis, and every node links to an
+/// associated range-tree on the next axis over the points of its subtree,
+/// down to a binary search tree on the last axis.
+///
+/// Points are any type `P` with `D` coordinates, such as `[f32; D]`, and each
+/// point holds a value of type `V` (nothing by default), which is kept once
+/// alongside its point in a list of items that the leaves refer to. The nodes
+/// (including the nodes of every associated structure) are stored in a single
+/// flat arena and linked by index.
+///
+/// Every node stores the number of points in its subtree along with their
+/// aggregate `A` (nothing by default), so counting and aggregate range queries
+/// use the canonical subtrees without visiting their leaves.
+///
+/// Each search tree sorts its points by the composite-number order of
+/// `compare_from` along its axis, so points with equal coordinates are split
+/// between the subtrees in a consistent order. Every point of a left subtree
+/// is then at or below the coordinate of the split-point, and every point of
+/// a right subtree is at or above it, which the searches rely on.
 #[derive(Debug, PartialEq)]
-pub struct RangeTree {
-    root: Node<f32>,
+pub struct RangeTree<const D: usize, P: Coordinates<D> = [f32; D], V = (), A: Aggregate<P, V> = ()>
+{
+    items: Vec<(P, V)>,
+    nodes: Vec<Node<P, A::Value>>,
+    root: Child,
 }
 
-impl RangeTree {
+/// A range-tree over points in the plane.
+pub type RangeTree2D = RangeTree<2, Point<f32>>;
+
+impl<const D: usize, P: Coordinates<D>> RangeTree<D, P> {
     /// Builds a range-tree for the set of known points `p`.
-    pub fn construct(points: Vec<Point<f32>>) -> Self {
-        // build the main tree T on the x-coordinate axis
-        let mut x_sorted: Vec<&Point<f32>> = points.iter().collect();
-        x_sorted.sort_by(|a, b| match a.x().partial_cmp(&b.x()).unwrap() {
-            Ordering::Equal => a.y().partial_cmp(&b.y()).unwrap(),
-            Ordering::Greater => Ordering::Greater,
-            Ordering::Less => Ordering::Less,
-        });
-
-        // build the main tree
-        Self {
-            root: Self::build_main_struct(x_sorted),
-        }
+    pub fn construct(points: Vec<P>) -> Self {
+        Self::construct_aggregate(points)
     }
+}
 
-    /// Builds the main search tree T (assumes points are already sorted).
-    fn build_main_struct(points: Vec<&Point<f32>>) -> Node<f32> {
-        // build the associated structure T_assoc according to y
-        let mut y_sorted: Vec<&&Point<f32>> = points.iter().collect();
-        y_sorted.sort_by(|a, b| match a.y().partial_cmp(&b.y()).unwrap() {
-            Ordering::Equal => a.x().partial_cmp(&b.x()).unwrap(),
-            Ordering::Greater => Ordering::Greater,
-            Ordering::Less => Ordering::Less,
-        });
+impl<const D: usize, P: Coordinates<D>, A: Aggregate<P>> RangeTree<D, P, (), A> {
+    /// Builds a range-tree for the set of known points `p` that also stores
+    /// the aggregate `A` of every subtree.
+    pub fn construct_aggregate(points: Vec<P>) -> Self {
+        Self::construct_aggregate_items(points.into_iter().map(|p| (p, ())).collect())
+    }
+}
 
-        let t_assoc = Self::build_assoc_struct(y_sorted);
+impl<const D: usize, P: Coordinates<D>, V> RangeTree<D, P, V> {
+    /// Builds a range-tree for the set of known points holding values `items`.
+    pub fn construct_items(items: Vec<(P, V)>) -> Self {
+        Self::construct_aggregate_items(items)
+    }
+}
 
-        match points.len() {
-            1 => Node::new(
-                **points.get(0).unwrap(),
-                None,
-                None,
-                Some(Box::new(t_assoc)),
-            ),
-            _ => {
-                // partition by the middle index
-                let middle = **points.get(points.len() / 2).unwrap();
-                let (left, right) = points.split_at(points.len() / 2);
+impl<const D: usize, P: Coordinates<D>, V, A: Aggregate<P, V>> RangeTree<D, P, V, A> {
+    /// Builds a range-tree for the set of known points holding values `items`
+    /// that also stores the aggregate `A` of every subtree.
+    ///
+    /// - Time complexity: O(n log^d(n)) for d dimensions
+    pub fn construct_aggregate_items(items: Vec<(P, V)>) -> Self {
+        let indices: Vec<usize> = (0..items.len()).collect();
 
-                let left_child = Self::build_main_struct(Vec::from(left));
-                let right_child = Self::build_main_struct(Vec::from(right));
+        // build the main tree T on the first axis
+        let mut nodes = Vec::new();
+        let root = match items.is_empty() {
+            true => None,
+            false => Some(Self::build_tree(&mut nodes, &items, &indices, 0)),
+        };
+        Self { items, nodes, root }
+    }
 
-                // create a new node with median point to describe the split-line
-                Node::new(
-                    middle,
-                    Some(Box::new(left_child)),
-                    Some(Box::new(right_child)),
-                    Some(Box::new(t_assoc)),
-                )
-            }
-        }
+    /// Returns the node stored at the index `child` in the arena.
+    fn node(&self, child: &Child) -> &Node<P, A::Value> {
+        &self.nodes[child.unwrap()]
+    }
+
+    /// Creates a leaf storing the point of the item at index `i`.
+    fn leaf(items: &[(P, V)], i: usize, assoc: Child) -> Node<P, A::Value> {
+        let (p, v) = &items[i];
+        Node::new(*p, i, None, None, assoc, 1, A::weight(p, v))
+    }
+
+    /// Creates a node for the split-line through `middle` above the subtrees
+    /// at `left` and `right`.
+    fn split(
+        nodes: &[Node<P, A::Value>],
+        middle: P,
+        left: usize,
+        right: usize,
+        assoc: Child,
+    ) -> Node<P, A::Value> {
+        let (l, r) = (&nodes[left], &nodes[right]);
+        Node::new(
+            middle,
+            0,
+            Some(left),
+            Some(right),
+            assoc,
+            l.count + r.count,
+            l.agg.combine(r.agg),
+        )
+    }
+
+    /// Builds the search tree on `axis` over the items at the indices `points`,
+    /// along with the associated structures on the remaining axes.
+    fn build_tree(
+        nodes: &mut Vec<Node<P, A::Value>>,
+        items: &[(P, V)],
+        points: &[usize],
+        axis: usize,
+    ) -> usize {
+        let mut sorted: Vec<usize> = points.to_vec();
+        sorted.sort_by(|&a, &b| compare_from(&items[a].0, &items[b].0, axis));
+        Self::build_sorted(nodes, items, &sorted, axis)
     }
 
-    /// Builds the binary search tree T_assoc (assumes points are already sorted).
-    fn build_assoc_struct(points: Vec<&&Point<f32>>) -> Node<f32> {
+    /// Recursive function call to build the search tree on `axis` over the
+    /// items at the indices `points` (assumes points are already sorted along
+    /// `axis`).
+    fn build_sorted(
+        nodes: &mut Vec<Node<P, A::Value>>,
+        items: &[(P, V)],
+        points: &[usize],
+        axis: usize,
+    ) -> usize {
+        // build the associated structure T_assoc on the next axis
+        let t_assoc = match axis + 1 < D {
+            true => Some(Self::build_tree(nodes, items, points, axis + 1)),
+            false => None,
+        };
+
         match points.len() {
-            1 => Node::new(***points.get(0).unwrap(), None, None, None),
+            1 => {
+                nodes.push(Self::leaf(items, points[0], t_assoc));
+                nodes.len() - 1
+            }
             _ => {
                 // partition by the middle index
-                let middle = ***points.get(points.len() / 2).unwrap();
+                let middle = items[points[points.len() / 2]].0;
                 let (left, right) = points.split_at(points.len() / 2);
 
-                let left_child = Self::build_assoc_struct(Vec::from(left));
-                let right_child = Self::build_assoc_struct(Vec::from(right));
+                let left_child = Self::build_sorted(nodes, items, left, axis);
+                let right_child = Self::build_sorted(nodes, items, right, axis);
 
                 // create a new node with median point to describe the split-line
-                Node::new(
-                    middle,
-                    Some(Box::new(left_child)),
-                    Some(Box::new(right_child)),
-                    None,
-                )
+                nodes.push(Self::split(nodes, middle, left_child, right_child, t_assoc));
+                nodes.len() - 1
             }
         }
     }
 
-    /// Asks a rectangular range query question for the rectangle defined by
+    /// Returns the number of points stored in the tree.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if the tree stores no points.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Asks a rectangular range query question for the box defined by
+    /// `region` (such as a `Region` when in the plane).
+    ///
+    /// - Time complexity: O(log^d(n) + k) for d dimensions
+    pub fn range_query<R: Into<Bounds<D>> + Copy>(&self, region: &R) -> Vec<&P> {
+        self.range_iter(region).collect()
+    }
+
+    /// Asks a rectangular range query question for the box defined by
+    /// `region` to return the points along with their values.
+    pub fn range_query_items<R: Into<Bounds<D>> + Copy>(&self, region: &R) -> Vec<(&P, &V)> {
+        self.range_iter_items(region).collect()
+    }
+
+    /// Iterates over the points contained within the box defined by `region`,
+    /// so the caller can stop early.
+    ///
+    /// The O(log^d(n)) canonical subtrees are selected up front, while their
+    /// leaves are only visited as the iterator advances.
+    pub fn range_iter<R: Into<Bounds<D>> + Copy>(
+        &self,
+        region: &R,
+    ) -> impl Iterator<Item = &P> + '_ {
+        // the leaves keep a copy of their point, which spares a lookup
+        self.range_leaves(region).map(|n| &n.data)
+    }
+
+    /// Iterates over the points contained within the box defined by `region`
+    /// along with their values, so the caller can stop early.
+    pub fn range_iter_items<R: Into<Bounds<D>> + Copy>(
+        &self,
+        region: &R,
+    ) -> impl Iterator<Item = (&P, &V)> + '_ {
+        self.range_leaves(region).map(|n| {
+            let (p, v) = &self.items[n.item];
+            (p, v)
+        })
+    }
+
+    /// Starts the walk over the leaves of the canonical subtrees for the range
     /// `region`.
-    pub fn range_query(&self, region: &Region<f32>) -> Vec<&Point<f32>> {
-        self.search_tree(&region, &self.root, Vec::new())
+    fn range_leaves<R: Into<Bounds<D>> + Copy>(
+        &self,
+        region: &R,
+    ) -> SubtreeLeaves<'_, P, A::Value> {
+        let mut stack = Vec::new();
+        self.search_region(region, &mut |node| stack.push(node));
+        // visit the subtrees in the order they were selected
+        stack.reverse();
+        SubtreeLeaves {
+            nodes: &self.nodes,
+            stack,
+        }
     }
 
-    /// Traverses the (sub)tree rooted at `node` to return the vertex that is
-    /// within the bounds of the x-axis range.
-    fn find_split_node_x<'a>(node: &'a Node<f32>, min_x: f32, max_x: f32) -> &'a Node<f32> {
-        let mut v = node;
-        while v.is_leaf() == false && (v.data.x() > max_x || v.data.x() < min_x) {
-            // too far right... try to go left
-            if v.data.x() > max_x {
-                v = v.left.as_ref().unwrap();
-            // too far left... try to go right
-            } else {
-                v = v.right.as_ref().unwrap();
-            }
+    /// Counts the points contained within the box defined by `region`.
+    ///
+    /// - Time complexity: O(log^d(n)) for d dimensions
+    pub fn range_count<R: Into<Bounds<D>> + Copy>(&self, region: &R) -> usize {
+        let mut count = 0;
+        self.search_region(region, &mut |node| count += node.count);
+        count
+    }
+
+    /// Combines the aggregate values of the points contained within the box
+    /// defined by `region`.
+    ///
+    /// - Time complexity: O(log^d(n)) for d dimensions
+    pub fn range_aggregate<R: Into<Bounds<D>> + Copy>(&self, region: &R) -> A::Value {
+        let mut agg = A::Value::identity();
+        self.search_region(region, &mut |node| agg = agg.combine(node.agg));
+        agg
+    }
+
+    /// Reports the canonical subtrees for the range `region` to `report`.
+    fn search_region<'a, R: Into<Bounds<D>> + Copy>(
+        &'a self,
+        region: &R,
+        report: &mut impl FnMut(&'a Node<P, A::Value>),
+    ) {
+        let region: Bounds<D> = (*region).into();
+        if let Some(root) = self.root {
+            self.search_tree(&region, &self.nodes[root], 0, report);
         }
-        v
     }
 
-    /// Traverses the (sub)tree rooted at `node` to return the vertex that is
-    /// within the bounds of the y-axis range.
-    fn find_split_node_y<'a>(node: &'a Node<f32>, min_y: f32, max_y: f32) -> &'a Node<f32> {
+    /// Traverses the (sub)tree rooted at `node` to return the vertex where the
+    /// paths to both boundaries of the range along `axis` split.
+    ///
+    /// The range `[min, max]` stands for the composite range from `(min|-inf)`
+    /// to `(max|+inf)`, so comparing the coordinate along `axis` alone orders
+    /// any point against the range exactly as its composite number would.
+    fn find_split_node<'a>(
+        &'a self,
+        node: &'a Node<P, A::Value>,
+        region: &Bounds<D>,
+        axis: usize,
+    ) -> &'a Node<P, A::Value> {
+        let (min, max) = (region.min(axis), region.max(axis));
         let mut v = node;
-        while v.is_leaf() == false && (v.data.y() > max_y || v.data.y() < min_y) {
+        while !v.is_leaf() && (v.data.coord(axis) > max || v.data.coord(axis) < min) {
             // too far right... try to go left
-            if v.data.y() > max_y {
-                v = v.left.as_ref().unwrap();
+            if v.data.coord(axis) > max {
+                v = self.node(&v.left);
             // too far left... try to go right
             } else {
-                v = v.right.as_ref().unwrap();
+                v = self.node(&v.right);
             }
         }
         v
     }
 
-    /// Performs a 1-dimensional range query on the (sub)tree rooted at `node`
-    /// to return all nodes contained within the range `region`.
+    /// Reports the subtree `node`, whose points are all within the range
+    /// along the axes before `axis`, once its points are also known to be
+    /// within the range along `axis`.
     ///
-    /// For the range-tree implementation, the 1-dimensional range query is
-    /// performed on the y-axis.
-    fn range_query_1d<'a>(
+    /// Unless `axis` is the last axis, the associated structure of `node` is
+    /// queried on the next axis.
+    fn search_next<'a>(
         &'a self,
-        node: &'a Node<f32>,
-        region: &Region<f32>,
-        mut result: Vec<&'a Point<f32>>,
-    ) -> Vec<&'a Point<f32>> {
-        let v_split = Self::find_split_node_y(node, region.l_y(), region.r_y());
-        match v_split.is_leaf() {
-            true => {
-                if region.contains_point(&v_split.data) == true {
-                    result.push(&v_split.data);
-                }
-            }
-            false => {
-                // follow the path to lowermost boundary and report the points in the subtrees right of the path
-                let mut v = v_split.left.as_ref().unwrap();
-                while v.is_leaf() == false {
-                    if v.data.y() >= region.l_y() {
-                        result = self.report_subtree(v.right.as_ref().unwrap(), result);
-                        v = v.left.as_ref().unwrap();
-                    } else {
-                        v = v.right.as_ref().unwrap();
-                    }
-                }
-                // check if the point stored in the leaf v must be reported
-                if region.contains_point(&v.data) == true {
-                    result.push(&v.data);
-                }
-
-                // follow the path to uppermost boundary and report the points in the subtrees left of the path
-                v = v_split.right.as_ref().unwrap();
-                while v.is_leaf() == false {
-                    if v.data.y() <= region.r_y() {
-                        result = self.report_subtree(v.left.as_ref().unwrap(), result);
-                        v = v.right.as_ref().unwrap();
-                    } else {
-                        v = v.left.as_ref().unwrap();
-                    }
-                }
-                // check if the point stored in the leaf v must be reported
-                if region.contains_point(&v.data) == true {
-                    result.push(&v.data);
-                }
-            }
+        region: &Bounds<D>,
+        node: &'a Node<P, A::Value>,
+        axis: usize,
+        report: &mut impl FnMut(&'a Node<P, A::Value>),
+    ) {
+        match node.is_last_dimension() {
+            true => report(node),
+            false => self.search_tree(region, self.node(node.next_dimension()), axis + 1, report),
         }
-        result
     }
 
     /// Recursive function call that traverses down the (sub)tree from `node`
     /// and selects the canonical subsets of the points that are defined within
-    /// the x-axis range. The selected subsets are chosen to conduct a 1D range
-    /// query along the y-axis range.
+    /// the range along `axis`. The selected subsets are chosen to conduct a
+    /// range query on the next axis, until the canonical subtrees of the
+    /// structures on the last axis are reported to `report`.
     fn search_tree<'a>(
         &'a self,
-        region: &Region<f32>,
-        node: &'a Node<f32>,
-        mut result: Vec<&'a Point<f32>>,
-    ) -> Vec<&Point<f32>> {
-        let v_split = Self::find_split_node_x(node, region.l_x(), region.r_x());
+        region: &Bounds<D>,
+        node: &'a Node<P, A::Value>,
+        axis: usize,
+        report: &mut impl FnMut(&'a Node<P, A::Value>),
+    ) {
+        let (min, max) = (region.min(axis), region.max(axis));
+        let v_split = self.find_split_node(node, region, axis);
         match v_split.is_leaf() {
             true => {
-                if region.contains_point(&v_split.data) == true {
-                    result.push(&v_split.data);
+                if region.contains_point(&v_split.data) {
+                    report(v_split);
                 }
             }
             false => {
-                // follow the path to leftmost boundary and report the points in the subtrees right of the path
-                let mut v = v_split.left.as_ref().unwrap();
-                while v.is_leaf() == false {
-                    if v.data.x() >= region.l_x() {
-                        result = self.range_query_1d(
-                            v.right.as_ref().unwrap().next_dimension().as_ref().unwrap(),
-                            region,
-                            result,
-                        );
-                        v = v.left.as_ref().unwrap();
+                // follow the path to lowermost boundary and query the subtrees right of the path
+                let mut v = self.node(&v_split.left);
+                while !v.is_leaf() {
+                    if v.data.coord(axis) >= min {
+                        self.search_next(region, self.node(&v.right), axis, report);
+                        v = self.node(&v.left);
                     } else {
-                        v = v.right.as_ref().unwrap();
+                        v = self.node(&v.right);
                     }
                 }
                 // check if the point stored in the leaf v must be reported
-                if region.contains_point(&v.data) == true {
-                    result.push(&v.data);
+                if region.contains_point(&v.data) {
+                    report(v);
                 }
 
-                // follow the path to rightmost boundary and report the points in the subtrees left of the path
-                v = v_split.right.as_ref().unwrap();
-                while v.is_leaf() == false {
-                    if v.data.x() <= region.r_x() {
-                        result = self.range_query_1d(
-                            v.left.as_ref().unwrap().next_dimension().as_ref().unwrap(),
-                            region,
-                            result,
-                        );
-                        v = v.right.as_ref().unwrap();
+                // follow the path to uppermost boundary and query the subtrees left of the path
+                v = self.node(&v_split.right);
+                while !v.is_leaf() {
+                    if v.data.coord(axis) <= max {
+                        self.search_next(region, self.node(&v.left), axis, report);
+                        v = self.node(&v.right);
                     } else {
-                        v = v.left.as_ref().unwrap();
+                        v = self.node(&v.left);
                     }
                 }
                 // check if the point stored in the leaf v must be reported
-                if region.contains_point(&v.data) == true {
-                    result.push(&v.data);
+                if region.contains_point(&v.data) {
+                    report(v);
                 }
             }
         }
-        result
     }
+}
 
-    /// Reports all leaves stored below `node` in the tree using an iterative
-    /// approach.
-    fn report_subtree<'a>(
-        &'a self,
-        node: &'a Node<f32>,
-        mut result: Vec<&'a Point<f32>>,
-    ) -> Vec<&Point<f32>> {
-        let mut stack = vec![node];
-        while stack.is_empty() == false {
-            let n = *stack.pop().as_ref().unwrap();
-            match n.is_leaf() {
-                true => {
-                    result.push(&n.data);
-                }
-                false => {
-                    if let Some(left) = &n.left {
-                        stack.push(left);
-                    }
-                    if let Some(right) = &n.right {
-                        stack.push(right);
-                    }
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layered_range_tree::LayeredRangeTree;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Sorts the points by increasing x-coordinate, and then increasing y-coordinate.
+    fn sorted(mut points: Vec<&Point<f32>>) -> Vec<&Point<f32>> {
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        points
+    }
+
+    #[test]
+    fn ut_range_query_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let points: Vec<Point<f32>> = (0..500)
+            .map(|_| Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)))
+            .collect();
+        let tree = RangeTree::construct(points.clone());
+        for _ in 0..50 {
+            let region = Region::new(
+                Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)),
+                Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)),
+            );
+            let expected: Vec<&Point<f32>> =
+                points.iter().filter(|p| region.contains_point(p)).collect();
+            let first: Vec<&Point<f32>> = tree.range_iter(&region).take(3).collect();
+            assert_eq!(first.len(), expected.len().min(3));
+            assert!(first.iter().all(|p| region.contains_point(p)));
+            assert_eq!(sorted(tree.range_query(&region)), sorted(expected));
+        }
+    }
+
+    #[test]
+    fn ut_degenerate_brute_force() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for points in crate::fixtures::degenerate_points(&mut rng, 200) {
+            let tree = RangeTree::construct(points.clone());
+            let layered = LayeredRangeTree::construct(points.clone());
+            for region in crate::fixtures::grid_regions() {
+                let expected: Vec<&Point<f32>> =
+                    points.iter().filter(|p| region.contains_point(p)).collect();
+                assert_eq!(sorted(tree.range_query(&region)), sorted(expected.clone()));
+                assert_eq!(tree.range_count(&region), expected.len());
+                assert_eq!(
+                    sorted(layered.range_query(&region)),
+                    sorted(expected.clone())
+                );
+                assert_eq!(layered.range_count(&region), expected.len());
+            }
+        }
+    }
+
+    /// Sums, and finds the extremes of, the y-coordinates of the points.
+    struct Heights;
+
+    impl Aggregate<Point<f32>> for Heights {
+        type Value = (Sum, (Min, Max));
+
+        fn weight(p: &Point<f32>, _: &()) -> Self::Value {
+            (Sum(p.y()), (Min(p.y()), Max(p.y())))
+        }
+    }
+
+    #[test]
+    fn ut_range_aggregate_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2);
+        // grid points keep the sums exact
+        let points: Vec<Point<f32>> = (0..500)
+            .map(|_| Point::from((rng.gen_range(0..32) as f32, rng.gen_range(0..32) as f32)))
+            .collect();
+        let tree: RangeTree<2, Point<f32>, (), Heights> =
+            RangeTree::construct_aggregate(points.clone());
+        for _ in 0..50 {
+            let region = Region::new(
+                Point::from((rng.gen_range(0..32) as f32, rng.gen_range(0..32) as f32)),
+                Point::from((rng.gen_range(0..32) as f32, rng.gen_range(0..32) as f32)),
+            );
+            let inside: Vec<&Point<f32>> =
+                points.iter().filter(|p| region.contains_point(p)).collect();
+            assert_eq!(tree.range_count(&region), inside.len());
+
+            let (sum, (min, max)) = tree.range_aggregate(&region);
+            assert_eq!(sum.0, inside.iter().map(|p| p.y()).sum::<f32>());
+            assert_eq!(
+                min.0,
+                inside.iter().map(|p| p.y()).fold(f32::INFINITY, f32::min)
+            );
+            assert_eq!(
+                max.0,
+                inside
+                    .iter()
+                    .map(|p| p.y())
+                    .fold(f32::NEG_INFINITY, f32::max)
+            );
+        }
+    }
+
+    #[test]
+    fn ut_items_with_duplicates() {
+        let mut rng = StdRng::seed_from_u64(3);
+        // grid points with many duplicate coordinates, labelled by a unique id
+        let items: Vec<(Point<f32>, usize)> = (0..300)
+            .map(|id| {
+                let p = Point::from((rng.gen_range(0..8) as f32, rng.gen_range(0..8) as f32));
+                (p, id)
+            })
+            .collect();
+        let tree = RangeTree::construct_items(items.clone());
+        for _ in 0..50 {
+            let region = Region::new(
+                Point::from((rng.gen_range(0..8) as f32, rng.gen_range(0..8) as f32)),
+                Point::from((rng.gen_range(0..8) as f32, rng.gen_range(0..8) as f32)),
+            );
+            let mut expected: Vec<usize> = items
+                .iter()
+                .filter(|(p, _)| region.contains_point(p))
+                .map(|(_, id)| *id)
+                .collect();
+            expected.sort();
+            let mut output: Vec<usize> = tree
+                .range_query_items(&region)
+                .into_iter()
+                .map(|(p, id)| {
+                    assert_eq!(*p, items[*id].0);
+                    *id
+                })
+                .collect();
+            output.sort();
+            assert_eq!(output, expected);
+        }
+    }
+
+    /// Checks the points found within the box `region` against a linear scan.
+    fn check_query<const D: usize>(tree: &RangeTree<D>, points: &[[f32; D]], region: &Bounds<D>) {
+        let cmp = |a: &&[f32; D], b: &&[f32; D]| a.partial_cmp(b).unwrap();
+        let mut expected: Vec<&[f32; D]> = points
+            .iter()
+            .filter(|p| region.contains_point(*p))
+            .collect();
+        expected.sort_by(cmp);
+        let mut output = tree.range_query(region);
+        output.sort_by(cmp);
+        assert_eq!(output, expected);
+        assert_eq!(tree.range_count(region), expected.len());
+    }
+
+    #[test]
+    fn ut_higher_dimensions() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for grid in [false, true] {
+            let mut sample = || match grid {
+                true => rng.gen_range(0..8) as f32,
+                false => rng.gen::<f32>() * 100.0,
+            };
+            let points: Vec<[f32; 1]> = (0..300).map(|_| [sample()]).collect();
+            let tree = RangeTree::construct(points.clone());
+            for _ in 0..20 {
+                check_query(&tree, &points, &Bounds::new([sample()], [sample()]));
+            }
+
+            let points: Vec<[f32; 3]> = (0..300)
+                .map(|_| std::array::from_fn(|_| sample()))
+                .collect();
+            let tree = RangeTree::construct(points.clone());
+            for _ in 0..20 {
+                let region = Bounds::new(
+                    std::array::from_fn(|_| sample()),
+                    std::array::from_fn(|_| sample()),
+                );
+                check_query(&tree, &points, &region);
+            }
+
+            let points: Vec<[f32; 4]> = (0..300)
+                .map(|_| std::array::from_fn(|_| sample()))
+                .collect();
+            let tree = RangeTree::construct(points.clone());
+            for _ in 0..20 {
+                let region = Bounds::new(
+                    std::array::from_fn(|_| sample()),
+                    std::array::from_fn(|_| sample()),
+                );
+                check_query(&tree, &points, &region);
             }
         }
-        result
     }
 }
@@ -0,0 +1,838 @@
+/// Project: euclid
+/// Module: rtree
+///
+/// This file contains functions to construct and query an R-tree data
+/// structure, which indexes objects by their bounding boxes.
+use crate::primitives::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// The strategy for choosing where to insert an entry and how to split a node
+/// that holds too many entries.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InsertRule {
+    /// Guttman's quadratic split: descend to the child whose box needs the
+    /// least enlargement, and split around the two entries that waste the most
+    /// area when grouped together.
+    Quadratic,
+    /// The R*-tree heuristics: descend to the leaf whose box overlaps its
+    /// siblings the least, and split along the axis with the smallest margins
+    /// into the groups that overlap the least (without forced reinsertion).
+    #[default]
+    RStar,
+}
+
+#[derive(Debug, PartialEq)]
+enum Entries<V> {
+    // the objects stored by a leaf along with their bounding boxes
+    Leaf(Vec<(Region<f32>, V)>),
+    // the children of an internal node
+    Internal(Vec<usize>),
+}
+
+#[derive(Debug, PartialEq)]
+struct Node<V> {
+    // the smallest box containing every entry below the node
+    mbr: Bounds<2>,
+    entries: Entries<V>,
+}
+
+impl<V> Node<V> {
+    pub fn is_leaf(&self) -> bool {
+        matches!(self.entries, Entries::Leaf(_))
+    }
+
+    /// Returns the number of entries stored directly in the node.
+    fn len(&self) -> usize {
+        match &self.entries {
+            Entries::Leaf(items) => items.len(),
+            Entries::Internal(children) => children.len(),
+        }
+    }
+}
+
+/// Computes the smallest box containing both `a` and `b`.
+fn union(a: &Bounds<2>, b: &Bounds<2>) -> Bounds<2> {
+    Bounds::new(
+        [a.min(0).min(b.min(0)), a.min(1).min(b.min(1))],
+        [a.max(0).max(b.max(0)), a.max(1).max(b.max(1))],
+    )
+}
+
+/// Computes the smallest box containing all of `boxes` (assumes at least 1).
+fn union_all<'a>(mut boxes: impl Iterator<Item = &'a Bounds<2>>) -> Bounds<2> {
+    let first = *boxes.next().unwrap();
+    boxes.fold(first, |acc, b| union(&acc, b))
+}
+
+fn area(b: &Bounds<2>) -> f32 {
+    (b.max(0) - b.min(0)) * (b.max(1) - b.min(1))
+}
+
+/// Computes half of the perimeter of the box `b`.
+fn margin(b: &Bounds<2>) -> f32 {
+    (b.max(0) - b.min(0)) + (b.max(1) - b.min(1))
+}
+
+/// Computes the area shared by the boxes `a` and `b`.
+fn overlap(a: &Bounds<2>, b: &Bounds<2>) -> f32 {
+    let width = a.max(0).min(b.max(0)) - a.min(0).max(b.min(0));
+    let height = a.max(1).min(b.max(1)) - a.min(1).max(b.min(1));
+    width.max(0.0) * height.max(0.0)
+}
+
+fn center(b: &Bounds<2>, axis: usize) -> f32 {
+    (b.min(axis) + b.max(axis)) / 2.0
+}
+
+/// Computes the distance from the point `p` to the closest point of the box
+/// `b`.
+fn box_dist(b: &Bounds<2>, p: &Point<f32>) -> f32 {
+    let dx = p.x() - p.x().clamp(b.min(0), b.max(0));
+    let dy = p.y() - p.y().clamp(b.min(1), b.max(1));
+    f32::sqrt(dx * dx + dy * dy)
+}
+
+/// Sorts `items` into groups of up to `size` with Sort-Tile-Recursive (STR):
+/// the items are cut into vertical slices by the centers of their boxes along
+/// the x-axis, and every slice is cut into groups along the y-axis.
+fn tile<T>(mut items: Vec<T>, size: usize, bounds: impl Fn(&T) -> Bounds<2>) -> Vec<Vec<T>> {
+    let groups = items.len().div_ceil(size);
+    let slices = (groups as f32).sqrt().ceil() as usize;
+    let per_slice = slices * size;
+
+    let by_center = |axis: usize, a: &T, b: &T| {
+        center(&bounds(a), axis)
+            .partial_cmp(&center(&bounds(b), axis))
+            .unwrap()
+    };
+    items.sort_by(|a, b| by_center(0, a, b));
+    let mut result = Vec::with_capacity(groups);
+    while !items.is_empty() {
+        let mut slice = items.split_off(items.len().saturating_sub(per_slice));
+        slice.sort_by(|a, b| by_center(1, a, b));
+        while !slice.is_empty() {
+            result.push(slice.split_off(slice.len().saturating_sub(size)));
+        }
+    }
+    result
+}
+
+/// A node or an object waiting to be visited during a nearest-object search,
+/// ordered so that the closest one is popped first from a max-heap.
+struct Candidate<'a, V> {
+    // lower bound on the distance to a node, or the distance to an object
+    dist: f32,
+    node: usize,
+    item: Option<&'a (Region<f32>, V)>,
+}
+
+impl<V> PartialEq for Candidate<'_, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.dist == other.dist
+    }
+}
+
+impl<V> Eq for Candidate<'_, V> {}
+
+impl<V> PartialOrd for Candidate<'_, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V> Ord for Candidate<'_, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.dist.partial_cmp(&self.dist).unwrap()
+    }
+}
+
+/// An R-tree over axis-aligned boxes in the plane.
+///
+/// Every node holds between `min_entries` and `max_entries` entries (except
+/// for the root and the last nodes of a bulk load), each covered by the node's
+/// minimum bounding rectangle (MBR), and all leaves are at the same depth.
+/// Unlike the point structures, the boxes of siblings may overlap, so a query
+/// may have to descend into more than one child of a node.
+///
+/// The value stored with each box identifies the object it bounds, such as a
+/// line segment, which can be indexed by converting it into its bounding
+/// `Region`.
+#[derive(Debug, PartialEq)]
+pub struct RTree<V> {
+    nodes: Vec<Node<V>>,
+    root: usize,
+    // slots in the arena of nodes that were freed by removals
+    free: Vec<usize>,
+    len: usize,
+    min_entries: usize,
+    max_entries: usize,
+    rule: InsertRule,
+}
+
+impl<V> RTree<V> {
+    /// Creates an empty R-tree whose nodes hold up to `max_entries` entries,
+    /// inserting entries by `rule`.
+    pub fn new(max_entries: usize, rule: InsertRule) -> Self {
+        assert!(max_entries >= 2, "a node must hold at least 2 entries");
+        Self {
+            nodes: vec![Node {
+                mbr: Bounds::new([0.0; 2], [0.0; 2]),
+                entries: Entries::Leaf(Vec::new()),
+            }],
+            root: 0,
+            free: Vec::new(),
+            len: 0,
+            // at least 40% of the nodes are used (as suggested for the R*-tree)
+            min_entries: (max_entries * 2 / 5).max(1),
+            max_entries,
+            rule,
+        }
+    }
+
+    /// Builds an R-tree for the known boxes holding values `items` by
+    /// Sort-Tile-Recursive (STR) bulk loading, which packs the nodes full and
+    /// keeps boxes that are close together within the same nodes. Later
+    /// insertions follow `rule`.
+    ///
+    /// - Time complexity: O(n log(n))
+    pub fn construct(items: Vec<(Region<f32>, V)>, max_entries: usize, rule: InsertRule) -> Self {
+        let mut tree = Self::new(max_entries, rule);
+        if items.is_empty() {
+            return tree;
+        }
+        tree.len = items.len();
+        tree.nodes.clear();
+
+        let mut level: Vec<usize> = tile(items, max_entries, |(r, _)| Bounds::from(*r))
+            .into_iter()
+            .map(|items| tree.alloc(Entries::Leaf(items)))
+            .collect();
+        while level.len() > 1 {
+            let nodes = &tree.nodes;
+            let groups = tile(level, max_entries, |&id| nodes[id].mbr);
+            level = groups
+                .into_iter()
+                .map(|children| tree.alloc(Entries::Internal(children)))
+                .collect();
+        }
+        tree.root = level[0];
+        tree
+    }
+
+    /// Computes the bounding box of `entries` (if any).
+    fn bounds(&self, entries: &Entries<V>) -> Option<Bounds<2>> {
+        let boxes: Vec<Bounds<2>> = match entries {
+            Entries::Leaf(items) => items.iter().map(|(r, _)| Bounds::from(*r)).collect(),
+            Entries::Internal(children) => children.iter().map(|&c| self.nodes[c].mbr).collect(),
+        };
+        match boxes.is_empty() {
+            true => None,
+            false => Some(union_all(boxes.iter())),
+        }
+    }
+
+    /// Stores a node holding `entries` in the arena, reusing a free slot if
+    /// possible.
+    fn alloc(&mut self, entries: Entries<V>) -> usize {
+        let node = Node {
+            mbr: self
+                .bounds(&entries)
+                .unwrap_or(Bounds::new([0.0; 2], [0.0; 2])),
+            entries,
+        };
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = node;
+                id
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Returns the number of boxes stored in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the tree stores no boxes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts the box `region` holding `value` into the tree.
+    ///
+    /// - Time complexity: O(log(n))
+    pub fn insert(&mut self, region: Region<f32>, value: V) {
+        if let Some(sibling) = self.insert_at(self.root, region, value) {
+            self.root = self.alloc(Entries::Internal(vec![self.root, sibling]));
+        }
+        self.len += 1;
+    }
+
+    /// Recursive function call that inserts the box `region` holding `value`
+    /// below `node`, and returns the new sibling of `node` if it had to be
+    /// split.
+    fn insert_at(&mut self, node: usize, region: Region<f32>, value: V) -> Option<usize> {
+        let b = Bounds::from(region);
+        self.nodes[node].mbr = match self.nodes[node].len() {
+            0 => b,
+            _ => union(&self.nodes[node].mbr, &b),
+        };
+        let child = match &self.nodes[node].entries {
+            Entries::Leaf(_) => None,
+            Entries::Internal(children) => Some(self.choose_subtree(children, &b)),
+        };
+        match child {
+            None => {
+                if let Entries::Leaf(items) = &mut self.nodes[node].entries {
+                    items.push((region, value));
+                }
+            }
+            Some(child) => {
+                if let Some(sibling) = self.insert_at(child, region, value) {
+                    if let Entries::Internal(children) = &mut self.nodes[node].entries {
+                        children.push(sibling);
+                    }
+                }
+            }
+        }
+        match self.nodes[node].len() > self.max_entries {
+            true => Some(self.split(node)),
+            false => None,
+        }
+    }
+
+    /// Chooses the child among `children` to insert the box `b` into.
+    fn choose_subtree(&self, children: &[usize], b: &Bounds<2>) -> usize {
+        let boxes: Vec<Bounds<2>> = children.iter().map(|&c| self.nodes[c].mbr).collect();
+        let enlargement = |i: usize| area(&union(&boxes[i], b)) - area(&boxes[i]);
+        let best = match (self.rule, self.nodes[children[0]].is_leaf()) {
+            // the overlap with the siblings that inserting into a leaf adds
+            (InsertRule::RStar, true) => (0..boxes.len())
+                .map(|i| {
+                    let grown = union(&boxes[i], b);
+                    let added: f32 = (0..boxes.len())
+                        .filter(|&j| j != i)
+                        .map(|j| overlap(&grown, &boxes[j]) - overlap(&boxes[i], &boxes[j]))
+                        .sum();
+                    (i, [added, enlargement(i), area(&boxes[i])])
+                })
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap()),
+            _ => (0..boxes.len())
+                .map(|i| (i, [enlargement(i), area(&boxes[i]), 0.0]))
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap()),
+        };
+        children[best.unwrap().0]
+    }
+
+    /// Splits the overflowing `node` into two by `rule`, keeping one group of
+    /// entries within `node` and returning the new node of the other group.
+    fn split(&mut self, node: usize) -> usize {
+        let boxes: Vec<Bounds<2>> = match &self.nodes[node].entries {
+            Entries::Leaf(items) => items.iter().map(|(r, _)| Bounds::from(*r)).collect(),
+            Entries::Internal(children) => children.iter().map(|&c| self.nodes[c].mbr).collect(),
+        };
+        let second = match self.rule {
+            InsertRule::Quadratic => self.quadratic_split(&boxes),
+            InsertRule::RStar => self.rstar_split(&boxes),
+        };
+        let sibling = match &mut self.nodes[node].entries {
+            Entries::Leaf(items) => Entries::Leaf(take_marked(items, &second)),
+            Entries::Internal(children) => Entries::Internal(take_marked(children, &second)),
+        };
+        self.nodes[node].mbr = self.bounds(&self.nodes[node].entries).unwrap();
+        self.alloc(sibling)
+    }
+
+    /// Partitions the `boxes` by Guttman's quadratic split, marking those that
+    /// belong to the second group.
+    fn quadratic_split(&self, boxes: &[Bounds<2>]) -> Vec<bool> {
+        // the pair of seeds that waste the most area in the same group
+        let mut seeds = (0, 1);
+        let mut worst = f32::NEG_INFINITY;
+        for i in 0..boxes.len() {
+            for j in i + 1..boxes.len() {
+                let waste = area(&union(&boxes[i], &boxes[j])) - area(&boxes[i]) - area(&boxes[j]);
+                if waste > worst {
+                    (seeds, worst) = ((i, j), waste);
+                }
+            }
+        }
+        let mut group: Vec<Option<bool>> = vec![None; boxes.len()];
+        group[seeds.0] = Some(false);
+        group[seeds.1] = Some(true);
+        let mut cover = [boxes[seeds.0], boxes[seeds.1]];
+        let mut count = [1, 1];
+
+        for remaining in (0..boxes.len() - 2).rev() {
+            // the group that needs every remaining entry to reach the minimum
+            let forced = (0..2).find(|&g| count[g] + remaining < self.min_entries);
+            // the entry with the strongest preference for one of the groups
+            let (next, growth) = (0..boxes.len())
+                .filter(|&i| group[i].is_none())
+                .map(|i| {
+                    let growth = cover.map(|c| area(&union(&c, &boxes[i])) - area(&c));
+                    (i, growth)
+                })
+                .max_by(|a, b| {
+                    let diff = |g: [f32; 2]| (g[0] - g[1]).abs();
+                    diff(a.1).partial_cmp(&diff(b.1)).unwrap()
+                })
+                .unwrap();
+            let g = match forced {
+                Some(g) => g,
+                None => {
+                    let key = |g: usize| (growth[g], area(&cover[g]), count[g]);
+                    match key(0).partial_cmp(&key(1)).unwrap() {
+                        Ordering::Greater => 1,
+                        _ => 0,
+                    }
+                }
+            };
+            group[next] = Some(g == 1);
+            cover[g] = union(&cover[g], &boxes[next]);
+            count[g] += 1;
+        }
+        group.into_iter().map(|g| g.unwrap()).collect()
+    }
+
+    /// Partitions the `boxes` by the R*-tree split, marking those that belong
+    /// to the second group.
+    ///
+    /// The boxes are sorted along each axis by their lower and by their upper
+    /// boundaries. The axis whose distributions into two groups have the
+    /// smallest sum of margins is chosen, and along it the distribution whose
+    /// groups overlap the least (then cover the least area).
+    fn rstar_split(&self, boxes: &[Bounds<2>]) -> Vec<bool> {
+        let (m, n) = (self.min_entries, boxes.len());
+        let mut sortings = Vec::with_capacity(4);
+        for axis in 0..2 {
+            for upper in [false, true] {
+                let mut order: Vec<usize> = (0..n).collect();
+                let key = |i: usize| match upper {
+                    true => (boxes[i].max(axis), boxes[i].min(axis)),
+                    false => (boxes[i].min(axis), boxes[i].max(axis)),
+                };
+                order.sort_by(|&a, &b| key(a).partial_cmp(&key(b)).unwrap());
+                sortings.push((axis, order));
+            }
+        }
+        let margins = |axis: usize| -> f32 {
+            sortings
+                .iter()
+                .filter(|(a, _)| *a == axis)
+                .flat_map(|(_, order)| distributions(boxes, order, m))
+                .map(|(_, a, b)| margin(&a) + margin(&b))
+                .sum()
+        };
+        let axis = match margins(0) <= margins(1) {
+            true => 0,
+            false => 1,
+        };
+        let (order, k) = sortings
+            .iter()
+            .filter(|(a, _)| *a == axis)
+            .flat_map(|(_, order)| {
+                distributions(boxes, order, m)
+                    .map(move |(k, a, b)| ((order, k), [overlap(&a, &b), area(&a) + area(&b)]))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .unwrap()
+            .0;
+        let mut second = vec![false; n];
+        for &i in &order[k..] {
+            second[i] = true;
+        }
+        second
+    }
+
+    /// Removes an entry whose box equals `region` from the tree to return its
+    /// value (if it exists).
+    ///
+    /// Nodes left with fewer than `min_entries` entries are dissolved and their
+    /// boxes are inserted again.
+    ///
+    /// - Time complexity: O(log(n)) on average, as overlapping boxes may
+    ///   require searching more than one path
+    pub fn remove(&mut self, region: &Region<f32>) -> Option<V> {
+        let b = Bounds::from(*region);
+        let mut orphans = Vec::new();
+        let value = self.remove_at(self.root, &b, &mut orphans)?;
+        self.len -= 1 + orphans.len();
+
+        // shorten the tree while the root has a single child
+        while let Entries::Internal(children) = &self.nodes[self.root].entries {
+            match children.len() {
+                0 => self.nodes[self.root].entries = Entries::Leaf(Vec::new()),
+                1 => {
+                    self.free.push(self.root);
+                    self.root = children[0];
+                }
+                _ => break,
+            }
+        }
+        for (region, value) in orphans {
+            self.insert(region, value);
+        }
+        Some(value)
+    }
+
+    /// Recursive function call that removes an entry with the box `b` below
+    /// `node`, collecting the entries of the dissolved nodes into `orphans`.
+    fn remove_at(
+        &mut self,
+        node: usize,
+        b: &Bounds<2>,
+        orphans: &mut Vec<(Region<f32>, V)>,
+    ) -> Option<V> {
+        if self.nodes[node].len() == 0 || !self.nodes[node].mbr.contains_bounds(b) {
+            return None;
+        }
+        let value = match &mut self.nodes[node].entries {
+            Entries::Leaf(items) => {
+                let i = items.iter().position(|(r, _)| Bounds::from(*r) == *b)?;
+                items.swap_remove(i).1
+            }
+            Entries::Internal(children) => {
+                let children = children.clone();
+                let (i, value) = children
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, &c)| self.remove_at(c, b, orphans).map(|v| (i, v)))?;
+                if self.nodes[children[i]].len() < self.min_entries {
+                    self.dissolve(children[i], orphans);
+                    if let Entries::Internal(children) = &mut self.nodes[node].entries {
+                        children.swap_remove(i);
+                    }
+                }
+                value
+            }
+        };
+        if let Some(mbr) = self.bounds(&self.nodes[node].entries) {
+            self.nodes[node].mbr = mbr;
+        }
+        Some(value)
+    }
+
+    /// Frees the subtree of `node`, collecting its entries into `orphans`.
+    fn dissolve(&mut self, node: usize, orphans: &mut Vec<(Region<f32>, V)>) {
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            match std::mem::replace(&mut self.nodes[id].entries, Entries::Internal(Vec::new())) {
+                Entries::Leaf(mut items) => orphans.append(&mut items),
+                Entries::Internal(children) => stack.extend(children),
+            }
+            self.free.push(id);
+        }
+    }
+
+    /// Finds all boxes intersecting `region` (including the boundary) along
+    /// with their values.
+    pub fn intersection_query(&self, region: &Region<f32>) -> Vec<(&Region<f32>, &V)> {
+        let q = Bounds::from(*region);
+        let mut result = Vec::new();
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id];
+            if !node.mbr.intersects(&q) {
+                continue;
+            }
+            match &node.entries {
+                Entries::Leaf(items) => result.extend(
+                    items
+                        .iter()
+                        .filter(|(r, _)| Bounds::from(*r).intersects(&q))
+                        .map(|(r, v)| (r, v)),
+                ),
+                Entries::Internal(children) => stack.extend(children),
+            }
+        }
+        result
+    }
+
+    /// Finds the box closest to the point `p` along with its value (a box
+    /// containing `p` has a distance of 0).
+    pub fn nearest(&self, p: &Point<f32>) -> Option<(&Region<f32>, &V)> {
+        self.nearest_by(p, |r, _| box_dist(&Bounds::from(*r), p))
+    }
+
+    /// Finds the object closest to the point `p` along with its box, where
+    /// `dist` measures the distance from `p` to the object of an entry.
+    ///
+    /// The search visits nodes and objects in order of increasing distance
+    /// (best-first), so `dist` must never be smaller than the distance from
+    /// `p` to the object's box. For instance, the distance to a line segment
+    /// finds the closest segment rather than the closest bounding box.
+    pub fn nearest_by(
+        &self,
+        p: &Point<f32>,
+        dist: impl Fn(&Region<f32>, &V) -> f32,
+    ) -> Option<(&Region<f32>, &V)> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut heap = BinaryHeap::new();
+        heap.push(Candidate {
+            dist: box_dist(&self.nodes[self.root].mbr, p),
+            node: self.root,
+            item: None,
+        });
+        while let Some(c) = heap.pop() {
+            if let Some((r, v)) = c.item {
+                return Some((r, v));
+            }
+            match &self.nodes[c.node].entries {
+                Entries::Leaf(items) => heap.extend(items.iter().map(|item| Candidate {
+                    dist: dist(&item.0, &item.1),
+                    node: c.node,
+                    item: Some(item),
+                })),
+                Entries::Internal(children) => {
+                    heap.extend(children.iter().map(|&child| Candidate {
+                        dist: box_dist(&self.nodes[child].mbr, p),
+                        node: child,
+                        item: None,
+                    }))
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Computes the bounding boxes of both groups for every split of the `boxes`
+/// in `order` after k entries, where both groups hold at least `m` entries.
+fn distributions<'a>(
+    boxes: &'a [Bounds<2>],
+    order: &'a [usize],
+    m: usize,
+) -> impl Iterator<Item = (usize, Bounds<2>, Bounds<2>)> + 'a {
+    (m..=order.len() - m).map(move |k| {
+        let first = union_all(order[..k].iter().map(|&i| &boxes[i]));
+        let second = union_all(order[k..].iter().map(|&i| &boxes[i]));
+        (k, first, second)
+    })
+}
+
+/// Moves the elements of `v` whose flag in `marked` is set into a new vector,
+/// keeping the others in order.
+fn take_marked<T>(v: &mut Vec<T>, marked: &[bool]) -> Vec<T> {
+    let mut kept = Vec::with_capacity(v.len());
+    let mut taken = Vec::with_capacity(v.len());
+    for (item, &mark) in std::mem::take(v).into_iter().zip(marked) {
+        match mark {
+            true => taken.push(item),
+            false => kept.push(item),
+        }
+    }
+    *v = kept;
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Samples boxes of varying sizes, with some degenerate ones.
+    fn sample_regions(rng: &mut impl Rng, size: usize) -> Vec<Region<f32>> {
+        (0..size)
+            .map(|_| {
+                let p = Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0));
+                let (w, h) = match rng.gen_range(0..4) {
+                    0 => (0.0, 0.0),
+                    _ => (rng.gen::<f32>() * 10.0, rng.gen::<f32>() * 10.0),
+                };
+                Region::new(p, Point::from((p.x() + w, p.y() + h)))
+            })
+            .collect()
+    }
+
+    fn sample_query(rng: &mut impl Rng) -> Region<f32> {
+        Region::new(
+            Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)),
+            Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)),
+        )
+    }
+
+    /// Computes the distance from the point `p` to the line segment `s`.
+    fn segment_dist(s: &LineSegment, p: &Point<f32>) -> f32 {
+        let (a, b) = (s.start(), s.end());
+        let (dx, dy) = (b.x() - a.x(), b.y() - a.y());
+        let len = dx * dx + dy * dy;
+        let t = match len > 0.0 {
+            true => (((p.x() - a.x()) * dx + (p.y() - a.y()) * dy) / len).clamp(0.0, 1.0),
+            false => 0.0,
+        };
+        euclid_dist(p, &Point::from((a.x() + t * dx, a.y() + t * dy)))
+    }
+
+    /// Checks that every box is covered by its node's box, that every node
+    /// holds no more than `max_entries` entries, and that every leaf is at the
+    /// same depth, returning the number of boxes below `node`.
+    fn check_invariants<V>(
+        tree: &RTree<V>,
+        node: usize,
+        depth: usize,
+        leaf_depth: &mut Option<usize>,
+    ) -> usize {
+        let n = &tree.nodes[node];
+        assert!(n.len() <= tree.max_entries);
+        match &n.entries {
+            Entries::Leaf(items) => {
+                assert_eq!(*leaf_depth.get_or_insert(depth), depth);
+                for (r, _) in items {
+                    assert!(n.mbr.contains_bounds(&Bounds::from(*r)));
+                }
+                items.len()
+            }
+            Entries::Internal(children) => children
+                .iter()
+                .map(|&c| {
+                    assert!(n.mbr.contains_bounds(&tree.nodes[c].mbr));
+                    check_invariants(tree, c, depth + 1, leaf_depth)
+                })
+                .sum(),
+        }
+    }
+
+    /// Sorts the boxes by their corners.
+    fn sorted(mut regions: Vec<&Region<f32>>) -> Vec<&Region<f32>> {
+        regions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        regions
+    }
+
+    #[test]
+    fn ut_intersection_query_brute_force() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let regions = sample_regions(&mut rng, 1_000);
+        let items: Vec<(Region<f32>, usize)> = regions.iter().copied().zip(0..).collect();
+        for rule in [InsertRule::Quadratic, InsertRule::RStar] {
+            let mut inserted = RTree::new(8, rule);
+            for (r, i) in &items {
+                inserted.insert(*r, *i);
+            }
+            let loaded = RTree::construct(items.clone(), 8, rule);
+            for tree in [&inserted, &loaded] {
+                assert_eq!(
+                    check_invariants(tree, tree.root, 0, &mut None),
+                    regions.len()
+                );
+                for _ in 0..50 {
+                    let q = sample_query(&mut rng);
+                    let expected = regions
+                        .iter()
+                        .filter(|r| Bounds::from(**r).intersects(&Bounds::from(q)))
+                        .collect();
+                    let output = tree
+                        .intersection_query(&q)
+                        .into_iter()
+                        .map(|(r, _)| r)
+                        .collect();
+                    assert_eq!(sorted(output), sorted(expected));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ut_insert_remove_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for rule in [InsertRule::Quadratic, InsertRule::RStar] {
+            let mut regions = sample_regions(&mut rng, 500);
+            let mut tree = RTree::construct(regions.iter().map(|r| (*r, ())).collect(), 6, rule);
+            for _ in 0..400 {
+                match rng.gen_bool(0.5) && !regions.is_empty() {
+                    true => {
+                        let r = regions.swap_remove(rng.gen_range(0..regions.len()));
+                        assert_eq!(tree.remove(&r), Some(()));
+                    }
+                    false => {
+                        let r = sample_regions(&mut rng, 1)[0];
+                        tree.insert(r, ());
+                        regions.push(r);
+                    }
+                }
+                assert_eq!(tree.len(), regions.len());
+            }
+            assert_eq!(
+                check_invariants(&tree, tree.root, 0, &mut None),
+                regions.len()
+            );
+            let q = Region::new(Point::from((0.0, 0.0)), Point::from((50.0, 50.0)));
+            let expected = regions
+                .iter()
+                .filter(|r| Bounds::from(**r).intersects(&Bounds::from(q)))
+                .collect();
+            let output = tree
+                .intersection_query(&q)
+                .into_iter()
+                .map(|(r, _)| r)
+                .collect();
+            assert_eq!(sorted(output), sorted(expected));
+
+            // empty the tree entirely
+            for r in regions.drain(..) {
+                assert_eq!(tree.remove(&r), Some(()));
+            }
+            assert!(tree.is_empty());
+            assert!(tree.intersection_query(&q).is_empty());
+            assert_eq!(tree.remove(&q), None);
+        }
+    }
+
+    #[test]
+    fn ut_nearest_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let regions = sample_regions(&mut rng, 500);
+        let tree = RTree::construct(
+            regions.iter().map(|r| (*r, ())).collect(),
+            8,
+            InsertRule::RStar,
+        );
+        for _ in 0..100 {
+            let p = Point::from((rng.gen::<f32>() * 120.0, rng.gen::<f32>() * 120.0));
+            let expected = regions
+                .iter()
+                .map(|r| box_dist(&Bounds::from(*r), &p))
+                .fold(f32::INFINITY, f32::min);
+            let (r, _) = tree.nearest(&p).unwrap();
+            assert_eq!(box_dist(&Bounds::from(*r), &p), expected);
+        }
+        assert!(RTree::<()>::new(4, InsertRule::Quadratic)
+            .nearest(&Point::new())
+            .is_none());
+    }
+
+    #[test]
+    fn ut_nearest_segment() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let segments: Vec<LineSegment> = (0..300)
+            .map(|_| {
+                let p = Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0));
+                let q = Point::from((
+                    p.x() + rng.gen::<f32>() * 20.0 - 10.0,
+                    p.y() + rng.gen::<f32>() * 20.0 - 10.0,
+                ));
+                LineSegment::from((p, q))
+            })
+            .collect();
+        let mut tree = RTree::new(8, InsertRule::Quadratic);
+        for s in &segments {
+            tree.insert(Region::from(s), s);
+        }
+        for _ in 0..100 {
+            let p = Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0));
+            let expected = segments
+                .iter()
+                .map(|s| segment_dist(s, &p))
+                .fold(f32::INFINITY, f32::min);
+            let (_, s) = tree.nearest_by(&p, |_, s| segment_dist(s, &p)).unwrap();
+            assert_eq!(segment_dist(s, &p), expected);
+        }
+    }
+}
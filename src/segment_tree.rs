@@ -0,0 +1,457 @@
+/// Project: euclid
+/// Module: segment_tree
+///
+/// This file contains functions to construct and query a segment tree over a
+/// set of non-crossing line segments, which answers vertical segment queries
+/// and windowing queries.
+use crate::primitives::*;
+use crate::range_tree::RangeTree;
+
+/// Index of a node within the tree's arena of nodes.
+type Child = Option<usize>;
+
+#[derive(Debug, PartialEq)]
+struct Node {
+    // the range of elementary intervals covered by the node
+    lo: usize,
+    hi: usize,
+    left: Child,
+    right: Child,
+    // the segments spanning the node's interval but not its parent's (the
+    // canonical subset), ordered from bottom to top
+    segments: Vec<usize>,
+}
+
+/// Computes the y-coordinate of the non-vertical segment `s` above the
+/// x-coordinate `x` (clamped to the segment's extent).
+fn y_at(s: &LineSegment, x: f32) -> f32 {
+    let (p, q) = (s.start(), s.end());
+    let t = (x.clamp(s.x_min(), s.x_max()) - p.x()) / (q.x() - p.x());
+    p.y() + t * (q.y() - p.y())
+}
+
+/// Checks if the non-vertical segment `s` intersects the vertical segment from
+/// `(x, y0)` to `(x, y1)`.
+fn crosses_vertical(s: &LineSegment, x: f32, y0: f32, y1: f32) -> bool {
+    s.x_min() < s.x_max() && s.x_min() <= x && x <= s.x_max() && {
+        let y = y_at(s, x);
+        y0 <= y && y <= y1
+    }
+}
+
+/// A segment tree over a set of non-crossing line segments in the plane.
+///
+/// The distinct x-coordinates of the endpoints split the x-axis into
+/// elementary intervals, which alternate between the open intervals between
+/// consecutive coordinates (even indices) and the coordinates themselves (odd
+/// indices). Every node covers a range of elementary intervals and stores the
+/// segments spanning its range but not its parent's range, so each segment is
+/// stored at O(log(n)) nodes.
+///
+/// Since the segments spanning a node do not cross, they can be ordered from
+/// bottom to top within the node's range, and a vertical query segment only
+/// searches the O(log(n)) nodes on the path to its x-coordinate.
+///
+/// Vertical segments are kept apart in a list sorted by x-coordinate, as they
+/// cannot cross a vertical query segment except by overlapping it.
+#[derive(Debug, PartialEq)]
+pub struct SegmentTree {
+    segments: Vec<LineSegment>,
+    // the distinct x-coordinates of the segments' endpoints in sorted order
+    xs: Vec<f32>,
+    nodes: Vec<Node>,
+    root: usize,
+    // the vertical segments ordered by x-coordinate, and then from bottom to top
+    verticals: Vec<usize>,
+}
+
+impl SegmentTree {
+    /// Builds a segment tree for the set of non-crossing `segments` (they may
+    /// share endpoints).
+    ///
+    /// - Time complexity: O(n log(n))
+    pub fn construct(segments: Vec<LineSegment>) -> Self {
+        let mut xs: Vec<f32> = segments
+            .iter()
+            .filter(|s| s.x_min() < s.x_max())
+            .flat_map(|s| [s.x_min(), s.x_max()])
+            .collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        xs.dedup();
+
+        let mut verticals: Vec<usize> = (0..segments.len())
+            .filter(|&id| segments[id].x_min() == segments[id].x_max())
+            .collect();
+        verticals.sort_by(|&a, &b| {
+            let (s, t) = (&segments[a], &segments[b]);
+            (s.x_min(), s.y_min())
+                .partial_cmp(&(t.x_min(), t.y_min()))
+                .unwrap()
+        });
+
+        let mut tree = Self {
+            segments,
+            nodes: Vec::new(),
+            root: 0,
+            xs,
+            verticals,
+        };
+        tree.root = tree.build(0, 2 * tree.xs.len());
+        for (id, s) in tree.segments.iter().enumerate() {
+            if s.x_min() < s.x_max() {
+                let lo = tree.elementary(s.x_min());
+                let hi = tree.elementary(s.x_max());
+                Self::insert(&mut tree.nodes, tree.root, lo, hi, id);
+            }
+        }
+        for id in 0..tree.nodes.len() {
+            let x = tree.sample_x(&tree.nodes[id]);
+            let segments = &tree.segments;
+            tree.nodes[id].segments.sort_by(|&a, &b| {
+                y_at(&segments[a], x)
+                    .partial_cmp(&y_at(&segments[b], x))
+                    .unwrap()
+            });
+        }
+        tree
+    }
+
+    /// Recursive function call to build the subtree over the elementary
+    /// intervals from `lo` to `hi` (inclusive).
+    fn build(&mut self, lo: usize, hi: usize) -> usize {
+        let (left, right) = match lo == hi {
+            true => (None, None),
+            false => {
+                let middle = (lo + hi) / 2;
+                (
+                    Some(self.build(lo, middle)),
+                    Some(self.build(middle + 1, hi)),
+                )
+            }
+        };
+        self.nodes.push(Node {
+            lo,
+            hi,
+            left,
+            right,
+            segments: Vec::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Recursive function call to store the segment `id` spanning the
+    /// elementary intervals from `lo` to `hi` at its canonical nodes below
+    /// `node`.
+    fn insert(nodes: &mut [Node], node: usize, lo: usize, hi: usize, id: usize) {
+        let n = &nodes[node];
+        if lo <= n.lo && n.hi <= hi {
+            nodes[node].segments.push(id);
+            return;
+        }
+        for child in [n.left, n.right].into_iter().flatten() {
+            let c = &nodes[child];
+            if lo <= c.hi && c.lo <= hi {
+                Self::insert(nodes, child, lo, hi, id);
+            }
+        }
+    }
+
+    /// Returns the index of the elementary interval containing `x`.
+    fn elementary(&self, x: f32) -> usize {
+        let i = self.xs.partition_point(|&v| v < x);
+        match self.xs.get(i) == Some(&x) {
+            true => 2 * i + 1,
+            false => 2 * i,
+        }
+    }
+
+    /// Returns an x-coordinate within the range of `node` where none of its
+    /// segments touch each other (unless the range is a single coordinate).
+    fn sample_x(&self, node: &Node) -> f32 {
+        match node.lo == node.hi && node.lo % 2 == 1 {
+            true => self.xs[node.lo / 2],
+            false => {
+                // the middle of the first open interval that is bounded
+                let e = (node.lo + node.lo % 2).max(2);
+                match self.xs.get(e / 2) {
+                    Some(&x) => (self.xs[e / 2 - 1] + x) / 2.0,
+                    // the node stores no segments
+                    None => 0.0,
+                }
+            }
+        }
+    }
+
+    /// Returns the stored segments.
+    pub fn segments(&self) -> &[LineSegment] {
+        &self.segments
+    }
+
+    /// Finds all segments intersecting the vertical segment from `(x, y0)` to
+    /// `(x, y1)`.
+    ///
+    /// - Time complexity: O(log^2(n) + k)
+    pub fn vertical_query(&self, x: f32, y0: f32, y1: f32) -> Vec<&LineSegment> {
+        let mut result = Vec::new();
+        self.search(x, y0, y1, &mut |id| result.push(&self.segments[id]));
+        // the vertical segments on the line through the query segment, which
+        // do not overlap each other and are thus also ordered by their top
+        let (y0, y1) = (y0.min(y1), y0.max(y1));
+        let first = self.verticals.partition_point(|&id| {
+            let s = &self.segments[id];
+            s.x_min() < x || (s.x_min() == x && s.y_max() < y0)
+        });
+        for &id in &self.verticals[first..] {
+            let s = &self.segments[id];
+            if s.x_min() > x || s.y_min() > y1 {
+                break;
+            }
+            result.push(s);
+        }
+        result
+    }
+
+    /// Follows the path from the root to the elementary interval containing
+    /// `x` to report the indices of the non-vertical segments intersecting the
+    /// vertical segment from `(x, y0)` to `(x, y1)` to `report`.
+    fn search(&self, x: f32, y0: f32, y1: f32, report: &mut impl FnMut(usize)) {
+        let (y0, y1) = (y0.min(y1), y0.max(y1));
+        let leaf = self.elementary(x);
+        let mut cur = Some(self.root);
+        while let Some(id) = cur {
+            let node = &self.nodes[id];
+            // the segments are ordered from bottom to top at x
+            let first = node
+                .segments
+                .partition_point(|&s| y_at(&self.segments[s], x) < y0);
+            for &s in &node.segments[first..] {
+                if y_at(&self.segments[s], x) > y1 {
+                    break;
+                }
+                report(s);
+            }
+            cur = [node.left, node.right]
+                .into_iter()
+                .flatten()
+                .find(|&c| self.nodes[c].lo <= leaf && leaf <= self.nodes[c].hi);
+        }
+    }
+}
+
+/// Swaps the x- and y-coordinates of the endpoints of the segment `s`.
+fn transpose(s: &LineSegment) -> LineSegment {
+    let (p, q) = (s.start(), s.end());
+    LineSegment::from((Point::from((p.y(), p.x())), Point::from((q.y(), q.x()))))
+}
+
+/// A structure for windowing queries over a set of non-crossing line segments,
+/// which reports the segments intersecting an axis-aligned query window.
+///
+/// A segment intersecting the window either has an endpoint inside it, found
+/// with a range-tree over the endpoints, or crosses its boundary twice. The
+/// crossings of the left and right edges are found with a segment tree over
+/// the segments, and the crossings of the bottom edge with a segment tree over
+/// the segments reflected across the diagonal (which turns the bottom edge
+/// into a vertical segment). Each segment is only reported by the first of
+/// these searches that finds it.
+#[derive(Debug, PartialEq)]
+pub struct WindowTree {
+    by_x: SegmentTree,
+    by_y: SegmentTree,
+    // the endpoints of the segments, holding the index of their segment and
+    // whether they are the end point
+    endpoints: RangeTree<2, Point<f32>, (usize, bool)>,
+}
+
+impl WindowTree {
+    /// Builds the windowing structure for the set of non-crossing `segments`
+    /// (they may share endpoints).
+    ///
+    /// - Time complexity: O(n log(n))
+    pub fn construct(segments: Vec<LineSegment>) -> Self {
+        let endpoints = segments
+            .iter()
+            .enumerate()
+            .flat_map(|(id, s)| [(*s.start(), (id, false)), (*s.end(), (id, true))])
+            .collect();
+        Self {
+            by_y: SegmentTree::construct(segments.iter().map(transpose).collect()),
+            by_x: SegmentTree::construct(segments),
+            endpoints: RangeTree::construct_items(endpoints),
+        }
+    }
+
+    /// Finds all segments intersecting the rectangle defined by `region`.
+    ///
+    /// - Time complexity: O(log^2(n) + k)
+    pub fn window_query(&self, region: &Region<f32>) -> Vec<&LineSegment> {
+        let segments = self.by_x.segments();
+        let (x0, x1, y0, y1) = (region.l_x(), region.r_x(), region.l_y(), region.r_y());
+        let has_endpoint =
+            |s: &LineSegment| region.contains_point(s.start()) || region.contains_point(s.end());
+        let mut result = Vec::new();
+
+        // segments with an endpoint inside the window (reported by the first one)
+        for (_, &(id, is_end)) in self.endpoints.range_query_items(region) {
+            if !is_end || !region.contains_point(segments[id].start()) {
+                result.push(&segments[id]);
+            }
+        }
+        // segments crossing the left edge and then the right edge
+        self.by_x.search(x0, y0, y1, &mut |id| {
+            if !has_endpoint(&segments[id]) {
+                result.push(&segments[id]);
+            }
+        });
+        self.by_x.search(x1, y0, y1, &mut |id| {
+            let s = &segments[id];
+            if !has_endpoint(s) && !crosses_vertical(s, x0, y0, y1) {
+                result.push(s);
+            }
+        });
+        // segments crossing the bottom edge (but neither the left nor right edge)
+        self.by_y.search(y0, x0, x1, &mut |id| {
+            let s = &segments[id];
+            if !has_endpoint(s)
+                && !crosses_vertical(s, x0, y0, y1)
+                && !crosses_vertical(s, x1, y0, y1)
+            {
+                result.push(s);
+            }
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Samples random segments and keeps those that do not intersect any
+    /// segment kept before them, including some vertical and horizontal ones.
+    fn sample_segments(rng: &mut impl Rng, size: usize) -> Vec<LineSegment> {
+        let mut segments: Vec<LineSegment> = Vec::new();
+        while segments.len() < size {
+            let p = Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0));
+            let (dx, dy) = (
+                rng.gen::<f32>() * 20.0 - 10.0,
+                rng.gen::<f32>() * 20.0 - 10.0,
+            );
+            let q = match rng.gen_range(0..4) {
+                0 => Point::from((p.x(), p.y() + dy)),
+                1 => Point::from((p.x() + dx, p.y())),
+                _ => Point::from((p.x() + dx, p.y() + dy)),
+            };
+            let s = LineSegment::from((p, q));
+            if segments.iter().all(|t| !segments_intersect(&s, t)) {
+                segments.push(s);
+            }
+        }
+        segments
+    }
+
+    /// Checks if the segment `s` intersects the rectangle defined by `region`.
+    fn intersects_window(s: &LineSegment, region: &Region<f32>) -> bool {
+        let corners = [
+            Point::from((region.l_x(), region.l_y())),
+            Point::from((region.r_x(), region.l_y())),
+            Point::from((region.r_x(), region.r_y())),
+            Point::from((region.l_x(), region.r_y())),
+        ];
+        region.contains_point(s.start())
+            || region.contains_point(s.end())
+            || (0..4).any(|i| {
+                let edge = LineSegment::from((corners[i], corners[(i + 1) % 4]));
+                segments_intersect(s, &edge)
+            })
+    }
+
+    /// Sorts the segments by their start point, and then their end point.
+    fn sorted(mut segments: Vec<&LineSegment>) -> Vec<&LineSegment> {
+        segments.sort_by(|a, b| {
+            (a.start(), a.end())
+                .partial_cmp(&(b.start(), b.end()))
+                .unwrap()
+        });
+        segments
+    }
+
+    #[test]
+    fn ut_vertical_query() {
+        let tree = SegmentTree::construct(vec![
+            LineSegment::from((Point::from((0.0, 0.0)), Point::from((4.0, 4.0)))),
+            LineSegment::from((Point::from((4.0, 4.0)), Point::from((8.0, 0.0)))),
+            LineSegment::from((Point::from((2.0, 6.0)), Point::from((6.0, 6.0)))),
+            LineSegment::from((Point::from((5.0, 1.0)), Point::from((5.0, 9.0)))),
+        ]);
+        assert_eq!(
+            tree.vertical_query(3.0, 0.0, 10.0),
+            vec![
+                &LineSegment::from((Point::from((0.0, 0.0)), Point::from((4.0, 4.0)))),
+                &LineSegment::from((Point::from((2.0, 6.0)), Point::from((6.0, 6.0)))),
+            ]
+        );
+        // both segments sharing the endpoint are found
+        assert_eq!(tree.vertical_query(4.0, 3.0, 5.0).len(), 2);
+        assert_eq!(tree.vertical_query(7.0, 2.0, 10.0).len(), 0);
+        // the vertical segment on the query line is found
+        assert_eq!(
+            tree.vertical_query(5.0, 8.0, 12.0),
+            vec![&LineSegment::from((
+                Point::from((5.0, 1.0)),
+                Point::from((5.0, 9.0))
+            ))]
+        );
+        assert_eq!(tree.vertical_query(5.0, 9.5, 12.0).len(), 0);
+    }
+
+    #[test]
+    fn ut_vertical_query_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let segments = sample_segments(&mut rng, 200);
+        let tree = SegmentTree::construct(
+            segments
+                .iter()
+                .map(|s| LineSegment::from((*s.start(), *s.end())))
+                .collect(),
+        );
+        for k in 0..200 {
+            // every other query lies on the line through an endpoint
+            let x = match k % 2 {
+                0 => rng.gen::<f32>() * 100.0,
+                _ => segments[rng.gen_range(0..segments.len())].x_min(),
+            };
+            let (y0, y1) = (rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0);
+            let query = LineSegment::from((Point::from((x, y0)), Point::from((x, y1))));
+            let expected = segments
+                .iter()
+                .filter(|s| segments_intersect(s, &query))
+                .collect();
+            assert_eq!(sorted(tree.vertical_query(x, y0, y1)), sorted(expected));
+        }
+    }
+
+    #[test]
+    fn ut_window_query_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let segments = sample_segments(&mut rng, 200);
+        let tree = WindowTree::construct(
+            segments
+                .iter()
+                .map(|s| LineSegment::from((*s.start(), *s.end())))
+                .collect(),
+        );
+        for _ in 0..100 {
+            let region = Region::new(
+                Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)),
+                Point::from((rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0)),
+            );
+            let expected = segments
+                .iter()
+                .filter(|s| intersects_window(s, &region))
+                .collect();
+            assert_eq!(sorted(tree.window_query(&region)), sorted(expected));
+        }
+    }
+}
@@ -0,0 +1,370 @@
+/// Project: euclid
+/// Module: skyline
+///
+/// This file contains geometric algorithms for computing the skyline (the set
+/// of maximal points, or Pareto front) of a given set of points in
+/// d-dimensional space.
+use std::cmp::Ordering;
+
+/// Checks if point `p` dominates point `q`.
+///
+/// A point dominates another point when it is at least as large along every
+/// axis and strictly larger along at least one axis.
+pub fn dominates<const D: usize>(p: &[f32; D], q: &[f32; D]) -> bool {
+    p.iter().zip(q).all(|(a, b)| a >= b) && p != q
+}
+
+/// Computes the skyline for a set of d-dimensional `points` by comparing every
+/// point against a window of the maximal points found so far.
+///
+/// The output is ordered by the position of the points within `points`.
+///
+/// - Time complexity: O(n^2)
+pub fn block_nested_loop<const D: usize>(points: &[[f32; D]]) -> Vec<&[f32; D]> {
+    let mut window: Vec<usize> = Vec::new();
+    for (i, p) in points.iter().enumerate() {
+        if window.iter().any(|&w| dominates(&points[w], p)) {
+            continue;
+        }
+        // evict the points in the window that are dominated by the new point
+        window.retain(|&w| !dominates(p, &points[w]));
+        window.push(i);
+    }
+    window.into_iter().map(|i| &points[i]).collect()
+}
+
+/// Computes the skyline for a set of d-dimensional `points` using the
+/// divide-and-conquer algorithm of Kung, Luccio, and Preparata.
+///
+/// The output is ordered by the position of the points within `points`.
+/// Duplicate points do not dominate one another, so every copy of a maximal
+/// point is reported.
+///
+/// - Time complexity: O(n*log(n)) for d <= 3, and O(n*log(n)^(d-2)) otherwise
+pub fn skyline<const D: usize>(points: &[[f32; D]]) -> Vec<&[f32; D]> {
+    // sort the point indices in decreasing lexicographic order
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| lexicographic(&points[b], &points[a]));
+
+    // collapse duplicates into a single representative point
+    let mut distinct: Vec<usize> = order.clone();
+    distinct.dedup_by(|a, b| points[*a] == points[*b]);
+
+    // sort the representatives once along every other axis
+    let mut set: Orders = vec![distinct; D];
+    for (axis, list) in set.iter_mut().enumerate().skip(1) {
+        list.sort_by(|&a, &b| points[b][axis].partial_cmp(&points[a][axis]).unwrap());
+    }
+
+    let mut is_maximal = vec![false; points.len()];
+    let mut marks = vec![false; points.len()];
+    for i in maxima(points, set, &mut marks).swap_remove(0) {
+        is_maximal[i] = true;
+    }
+    // propagate the result of each representative to its duplicates
+    for pair in order.windows(2) {
+        if points[pair[0]] == points[pair[1]] && is_maximal[pair[0]] {
+            is_maximal[pair[1]] = true;
+        }
+    }
+
+    points
+        .iter()
+        .zip(is_maximal)
+        .filter(|(_, m)| *m)
+        .map(|(p, _)| p)
+        .collect()
+}
+
+/// Compares two points by their coordinates in increasing axis order.
+fn lexicographic<const D: usize>(p: &[f32; D], q: &[f32; D]) -> Ordering {
+    for (a, b) in p.iter().zip(q) {
+        match a.partial_cmp(b).unwrap() {
+            Ordering::Equal => continue,
+            ordering => return ordering,
+        }
+    }
+    Ordering::Equal
+}
+
+/// The indices of a set of points, listed once per axis in decreasing order
+/// of their coordinate along that axis.
+///
+/// Only the lists of the axes that are still to be searched are kept up to
+/// date, and the last list always holds the entire set.
+type Orders = Vec<Vec<usize>>;
+
+/// Merges the lists `a` and `b`, both sorted by decreasing coordinate along
+/// `axis`, while tagging each index with whether it comes from `a` (the points
+/// of `a` go first on ties).
+fn merge<const D: usize>(
+    points: &[[f32; D]],
+    axis: usize,
+    a: &[usize],
+    b: &[usize],
+) -> Vec<(usize, bool)> {
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        match j == b.len() || (i < a.len() && points[a[i]][axis] >= points[b[j]][axis]) {
+            true => {
+                merged.push((a[i], true));
+                i += 1;
+            }
+            false => {
+                merged.push((b[j], false));
+                j += 1;
+            }
+        }
+    }
+    merged
+}
+
+/// Splits the lists of `set` from `axis` onwards into the points that are
+/// flagged in `marks` and the ones that are not, keeping them sorted.
+fn split_marked(set: &[Vec<usize>], marks: &[bool], axis: usize) -> (Orders, Orders) {
+    let mut marked = vec![Vec::new(); set.len()];
+    let mut other = vec![Vec::new(); set.len()];
+    for k in axis..set.len() {
+        (marked[k], other[k]) = set[k].iter().partition(|&&i| marks[i]);
+    }
+    (marked, other)
+}
+
+/// Keeps the points of `list` that are within the set `kept` (as given by its
+/// last list).
+fn retain_within(list: &mut Vec<usize>, kept: &Orders, marks: &mut [bool]) {
+    let kept = kept.last().unwrap();
+    kept.iter().for_each(|&i| marks[i] = true);
+    list.retain(|&i| marks[i]);
+    kept.iter().for_each(|&i| marks[i] = false);
+}
+
+/// Joins the disjoint sets `a` and `b` by merging their lists from `axis`
+/// onwards.
+fn union<const D: usize>(points: &[[f32; D]], a: Orders, b: Orders, axis: usize) -> Orders {
+    let mut joined = vec![Vec::new(); D];
+    for k in axis..D {
+        joined[k] = merge(points, k, &a[k], &b[k])
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect();
+    }
+    joined
+}
+
+/// Recursive function call to find the maximal points among the distinct
+/// points in `set` (whose list along the first axis is sorted in decreasing
+/// lexicographic order).
+///
+/// The flags in `marks` are all unset before and after the call.
+fn maxima<const D: usize>(points: &[[f32; D]], set: Orders, marks: &mut [bool]) -> Orders {
+    if set[0].len() <= 1 {
+        return set;
+    }
+    // no point in the lower half can dominate a point in the upper half
+    let half = &set[0][..set[0].len() / 2];
+    half.iter().for_each(|&i| marks[i] = true);
+    let (upper, lower) = split_marked(&set, marks, 0);
+    half.iter().for_each(|&i| marks[i] = false);
+
+    let upper = maxima(points, upper, marks);
+    let mut lower = maxima(points, lower, marks);
+    // every upper point is at least as large on the first axis as any lower point
+    let mut first = std::mem::take(&mut lower[0]);
+    let mut lower = filter(points, &upper, lower, 1, marks);
+    retain_within(&mut first, &lower, marks);
+    lower[0] = first;
+    union(points, upper, lower, 0)
+}
+
+/// Removes the points in `lower` that are dominated by a point in `upper`
+/// when only considering the axes from `axis` onwards.
+///
+/// Assumes the points in `upper` are already at least as large as the points
+/// in `lower` along every axis before `axis`, and that the points in both sets
+/// are distinct. Only the lists from `axis` onwards are read and returned.
+fn filter<const D: usize>(
+    points: &[[f32; D]],
+    upper: &Orders,
+    mut lower: Orders,
+    axis: usize,
+    marks: &mut [bool],
+) -> Orders {
+    if upper[D - 1].is_empty() || lower[D - 1].is_empty() {
+        return lower;
+    }
+    // the remaining axes are exhausted (every upper point dominates)
+    if axis == D {
+        return vec![Vec::new(); D];
+    }
+    // only one axis left: compare against the largest upper point
+    if axis == D - 1 {
+        let ceiling = points[upper[axis][0]][axis];
+        lower[axis].retain(|&l| points[l][axis] > ceiling);
+        return lower;
+    }
+
+    // merge both sets by decreasing coordinate along the axis, with upper points first on ties
+    let merged = merge(points, axis, &upper[axis], &lower[axis]);
+
+    // two axes left: sweep while tracking the highest upper point seen so far
+    if axis == D - 2 {
+        let mut ceiling = f32::NEG_INFINITY;
+        let mut result = Vec::new();
+        for (i, is_upper) in merged {
+            match is_upper {
+                true => ceiling = ceiling.max(points[i][axis + 1]),
+                false => {
+                    if points[i][axis + 1] > ceiling {
+                        result.push(i);
+                        marks[i] = true;
+                    }
+                }
+            }
+        }
+        lower[axis + 1].retain(|&i| marks[i]);
+        result.iter().for_each(|&i| marks[i] = false);
+        lower[axis] = result;
+        return lower;
+    }
+
+    // partition by the median index along the current axis
+    let (high, low) = merged.split_at(merged.len() / 2);
+    high.iter().for_each(|&(i, _)| marks[i] = true);
+    let (mut upper_hi, mut upper_lo) = split_marked(upper, marks, axis + 1);
+    let (mut lower_hi, mut lower_lo) = split_marked(&lower, marks, axis + 1);
+    high.iter().for_each(|&(i, _)| marks[i] = false);
+    for (half, upper, lower) in [
+        (high, &mut upper_hi, &mut lower_hi),
+        (low, &mut upper_lo, &mut lower_lo),
+    ] {
+        for &(i, is_upper) in half {
+            match is_upper {
+                true => upper[axis].push(i),
+                false => lower[axis].push(i),
+            }
+        }
+    }
+
+    // points in the high half can only be dominated by upper points in the high half
+    let mut lower_hi = filter(points, &upper_hi, lower_hi, axis, marks);
+    // points in the low half lie below the high half along this axis, so the next axis decides
+    let mut lower_lo = filter(points, &upper_lo, lower_lo, axis, marks);
+    let mut list = std::mem::take(&mut lower_lo[axis]);
+    let lower_lo = filter(points, &upper_hi, lower_lo, axis + 1, marks);
+    retain_within(&mut list, &lower_lo, marks);
+
+    // the high half comes before the low half along this axis
+    let head = std::mem::take(&mut lower_hi[axis]);
+    let mut result = union(points, lower_hi, lower_lo, axis + 1);
+    result[axis] = [head, list].concat();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::Point;
+    use crate::staircase::staircase;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Generate randomly sampled points in `D` dimensions.
+    fn sample_points<const D: usize>(
+        rng: &mut impl Rng,
+        size: usize,
+        grid: Option<u32>,
+    ) -> Vec<[f32; D]> {
+        (0..size)
+            .map(|_| {
+                let mut p = [0.0; D];
+                for c in p.iter_mut() {
+                    *c = match grid {
+                        Some(g) => rng.gen_range(0..g) as f32,
+                        None => rng.gen::<f32>() * 512.0,
+                    };
+                }
+                p
+            })
+            .collect()
+    }
+
+    fn check_against_baseline<const D: usize>(points: &[[f32; D]]) {
+        let expected: Vec<&[f32; D]> = points
+            .iter()
+            .filter(|p| points.iter().all(|q| !dominates(q, p)))
+            .collect();
+        assert_eq!(skyline(points), expected);
+        assert_eq!(block_nested_loop(points), expected);
+    }
+
+    #[test]
+    fn ut_skyline() {
+        let input = [
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [2.0, 0.0, 0.0],
+            [0.0, 2.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [0.0, 1.0, 3.0],
+            [0.0, 1.0, 2.0],
+        ];
+
+        let output = skyline(&input);
+
+        assert_eq!(
+            output,
+            vec![
+                &[1.0, 1.0, 1.0],
+                &[2.0, 0.0, 0.0],
+                &[0.0, 2.0, 0.0],
+                &[1.0, 1.0, 1.0],
+                &[0.0, 1.0, 3.0],
+            ]
+        );
+        assert_eq!(block_nested_loop(&input).len(), 5);
+    }
+
+    #[test]
+    fn ut_skyline_matches_staircase() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let input = sample_points::<2>(&mut rng, 300, None);
+            let points: Vec<Point<f32>> = input.iter().map(|p| Point::from((p[0], p[1]))).collect();
+
+            let mut expected: Vec<[f32; 2]> =
+                staircase(&points).iter().map(|p| [p.x(), p.y()]).collect();
+            expected.sort_by(lexicographic);
+            let mut output: Vec<[f32; 2]> = skyline(&input).into_iter().copied().collect();
+            output.sort_by(lexicographic);
+            let mut window: Vec<[f32; 2]> =
+                block_nested_loop(&input).into_iter().copied().collect();
+            window.sort_by(lexicographic);
+
+            assert_eq!(output, expected);
+            assert_eq!(window, expected);
+        }
+    }
+
+    #[test]
+    fn ut_skyline_higher_dimensions() {
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..5 {
+            check_against_baseline(&sample_points::<3>(&mut rng, 400, None));
+            check_against_baseline(&sample_points::<4>(&mut rng, 400, None));
+            check_against_baseline(&sample_points::<5>(&mut rng, 400, None));
+            check_against_baseline(&sample_points::<6>(&mut rng, 400, None));
+        }
+    }
+
+    #[test]
+    fn ut_skyline_duplicates() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..5 {
+            check_against_baseline(&sample_points::<2>(&mut rng, 200, Some(8)));
+            check_against_baseline(&sample_points::<3>(&mut rng, 200, Some(4)));
+            check_against_baseline(&sample_points::<4>(&mut rng, 200, Some(3)));
+            check_against_baseline(&sample_points::<6>(&mut rng, 200, Some(2)));
+        }
+    }
+}
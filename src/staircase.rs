@@ -6,6 +6,8 @@
 /// given set of points.
 use crate::primitives::*;
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
 
 pub fn staircase(points: &Vec<Point<f32>>) -> Vec<&Point<f32>> {
     // sort the points in decreasing x-coordinate
@@ -32,6 +34,188 @@ pub fn staircase(points: &Vec<Point<f32>>) -> Vec<&Point<f32>> {
     stairpoints.into_iter().rev().collect()
 }
 
+/// Assigns each point in `points` to its dominance layer by repeatedly
+/// peeling off the staircase (skyline) of the remaining points.
+///
+/// The returned list holds the layer of the point at the same index in
+/// `points`, where layer 0 is the staircase of the entire set. Duplicate points
+/// do not dominate one another, so every copy of a point falls into the same
+/// layer (as in `skyline::skyline`).
+///
+/// - Time complexity: O(n*log(n))
+pub fn maximal_layers(points: &[Point<f32>]) -> Vec<usize> {
+    // sort the point indices in decreasing x-coordinate, and then decreasing y-coordinate (if equal x-coordinate)
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&points[a], &points[b]);
+        match b.x().partial_cmp(&a.x()).unwrap() {
+            Ordering::Equal => b.y().partial_cmp(&a.y()).unwrap(),
+            ordering => ordering,
+        }
+    });
+
+    let mut layers = vec![0; points.len()];
+    // keep track of the floor of each layer (decreasing by layer)
+    let mut floors: Vec<f32> = Vec::new();
+    for (k, &i) in order.iter().enumerate() {
+        // copies are sorted next to each other and share their layer
+        if k > 0 && points[order[k - 1]] == points[i] {
+            layers[i] = layers[order[k - 1]];
+            continue;
+        }
+        let y = points[i].y();
+        // the first layer with a floor below the point does not dominate it
+        let layer = floors.partition_point(|&floor| floor >= y);
+        match layer == floors.len() {
+            // the point is dominated by every existing layer
+            true => floors.push(y),
+            // raise the floor
+            false => floors[layer] = y,
+        }
+        layers[i] = layer;
+    }
+    layers
+}
+
+/// A point ordered by increasing x-coordinate, and then increasing
+/// y-coordinate (if equal x-coordinate).
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Key(Point<f32>);
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.0.x().partial_cmp(&other.0.x()).unwrap() {
+            Ordering::Equal => self.0.y().partial_cmp(&other.0.y()).unwrap(),
+            ordering => ordering,
+        }
+    }
+}
+
+/// A staircase that is maintained while points are inserted into and deleted
+/// from the underlying set of points.
+///
+/// A point belongs to the staircase when no other point is at least as large
+/// on both axes. The staircase is kept in a balanced search tree ordered by
+/// x-coordinate, while the entire set of points is kept in a secondary search
+/// tree to restore the points that were hidden behind a deleted stair.
+#[derive(Debug, Default)]
+pub struct DynamicStaircase {
+    // the extremal points (increasing x-coordinate, decreasing y-coordinate)
+    stairs: BTreeSet<Key>,
+    // every known point and its number of copies
+    points: BTreeMap<Key, usize>,
+    len: usize,
+}
+
+impl DynamicStaircase {
+    /// Creates a staircase for an empty set of points.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of points in the set (including dominated points).
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the set of points is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the points on the staircase from left to right.
+    pub fn iter(&self) -> impl Iterator<Item = &Point<f32>> {
+        self.stairs.iter().map(|k| &k.0)
+    }
+
+    /// Adds the point `p` to the set and removes any stairs it dominates.
+    ///
+    /// - Time complexity: O(log(n) + k), for k removed stairs
+    pub fn insert(&mut self, p: Point<f32>) {
+        *self.points.entry(Key(p)).or_insert(0) += 1;
+        self.len += 1;
+
+        // the nearest stair at or to the right of p has the highest y-coordinate among those stairs
+        let lowest = Key(Point::from((p.x(), f32::NEG_INFINITY)));
+        if let Some(s) = self.stairs.range(lowest..).next() {
+            if s.0.y() >= p.y() {
+                return;
+            }
+        }
+        // lower the stairs to the left of p that are now dominated
+        let highest = Key(Point::from((p.x(), f32::INFINITY)));
+        let dominated: Vec<Key> = self
+            .stairs
+            .range(..highest)
+            .rev()
+            .take_while(|s| s.0.y() <= p.y())
+            .copied()
+            .collect();
+        for s in dominated {
+            self.stairs.remove(&s);
+        }
+        self.stairs.insert(Key(p));
+    }
+
+    /// Removes one copy of the point `p` from the set and restores any points
+    /// that were only dominated by `p`.
+    ///
+    /// Returns `false` if the point `p` was not found in the set.
+    ///
+    /// - Time complexity: O(log(n) + m), for m points in the x-axis strip below p
+    pub fn remove(&mut self, p: &Point<f32>) -> bool {
+        let key = Key(*p);
+        match self.points.get_mut(&key) {
+            None => return false,
+            Some(count) => {
+                *count -= 1;
+                self.len -= 1;
+                if *count > 0 {
+                    return true;
+                }
+            }
+        }
+        self.points.remove(&key);
+        if !self.stairs.remove(&key) {
+            return true;
+        }
+
+        // the neighboring stairs bound the points that were hidden by p
+        let left = self.stairs.range(..key).next_back().map(|s| s.0.x());
+        let floor = self
+            .stairs
+            .range(key..)
+            .next()
+            .map_or(f32::NEG_INFINITY, |s| s.0.y());
+        let start = match left {
+            Some(x) => Bound::Excluded(Key(Point::from((x, f32::INFINITY)))),
+            None => Bound::Unbounded,
+        };
+        let end = Bound::Included(Key(Point::from((p.x(), f32::INFINITY))));
+
+        // compute the staircase of the strip in decreasing x-coordinate
+        let mut floor = floor;
+        let mut restored = Vec::new();
+        for (q, _) in self.points.range((start, end)).rev() {
+            if q.0.y() > floor {
+                restored.push(*q);
+                // raise the floor
+                floor = q.0.y();
+            }
+        }
+        self.stairs.extend(restored);
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,4 +243,162 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn ut_maximal_layers() {
+        let input = vec![
+            Point::from((0.0, 0.0)),
+            Point::from((0.0, 5.0)),
+            Point::from((1.0, 4.0)),
+            Point::from((6.0, 2.0)),
+            Point::from((2.0, 1.0)),
+            Point::from((4.0, 0.0)),
+            Point::from((-1.0, 3.0)),
+        ];
+
+        let output = maximal_layers(&input);
+
+        assert_eq!(output, vec![2, 0, 0, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn ut_maximal_layers_peeling() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let input: Vec<Point<f32>> = (0..200)
+            .map(|_| Point::from((rng.gen::<f32>() * 512.0, rng.gen::<f32>() * 512.0)))
+            .collect();
+
+        let layers = maximal_layers(&input);
+
+        // peel off one staircase at a time and compare against the layers
+        let mut remaining = input.clone();
+        let mut layer = 0;
+        while !remaining.is_empty() {
+            let stairs: Vec<Point<f32>> = staircase(&remaining).into_iter().copied().collect();
+            for p in &stairs {
+                let i = input.iter().position(|q| q == p).unwrap();
+                assert_eq!(layers[i], layer);
+            }
+            remaining.retain(|p| !stairs.contains(p));
+            layer += 1;
+        }
+    }
+
+    #[test]
+    fn ut_maximal_layers_duplicates() {
+        use crate::skyline::skyline;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(26);
+        // grid points with many duplicate coordinates
+        let input: Vec<[f32; 2]> = (0..300)
+            .map(|_| [rng.gen_range(0..12) as f32, rng.gen_range(0..12) as f32])
+            .collect();
+
+        let points: Vec<Point<f32>> = input.iter().map(|&[x, y]| Point::from((x, y))).collect();
+        let layers = maximal_layers(&points);
+
+        // peel off one skyline at a time, which reports every copy of a maximal point
+        let mut remaining = input.clone();
+        let mut layer = 0;
+        while !remaining.is_empty() {
+            let maxima: Vec<[f32; 2]> = skyline(&remaining).into_iter().copied().collect();
+            for (p, &l) in input.iter().zip(&layers) {
+                assert_eq!(maxima.contains(p), l == layer);
+            }
+            remaining.retain(|p| !maxima.contains(p));
+            layer += 1;
+        }
+    }
+
+    #[test]
+    fn ut_dynamic_staircase() {
+        let mut stairs = DynamicStaircase::new();
+        for p in [(0.0, 0.0), (0.0, 5.0), (1.0, 4.0), (6.0, 2.0), (2.0, 1.0)] {
+            stairs.insert(Point::from(p));
+        }
+        assert_eq!(
+            stairs.iter().collect::<Vec<_>>(),
+            vec![
+                &Point::from((0.0, 5.0)),
+                &Point::from((1.0, 4.0)),
+                &Point::from((6.0, 2.0)),
+            ]
+        );
+
+        // deleting a stair restores the points it was hiding
+        assert!(stairs.remove(&Point::from((1.0, 4.0))));
+        assert!(!stairs.remove(&Point::from((1.0, 4.0))));
+        assert!(stairs.remove(&Point::from((6.0, 2.0))));
+        assert_eq!(
+            stairs.iter().collect::<Vec<_>>(),
+            vec![&Point::from((0.0, 5.0)), &Point::from((2.0, 1.0))]
+        );
+        assert_eq!(stairs.len(), 3);
+    }
+
+    #[test]
+    fn ut_dynamic_staircase_random() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut stairs = DynamicStaircase::new();
+        let mut points: Vec<Point<f32>> = Vec::new();
+        for _ in 0..2_000 {
+            match points.is_empty() || rng.gen_bool(0.6) {
+                true => {
+                    let p = Point::from((rng.gen::<f32>() * 512.0, rng.gen::<f32>() * 512.0));
+                    stairs.insert(p);
+                    points.push(p);
+                }
+                false => {
+                    let p = points.swap_remove(rng.gen_range(0..points.len()));
+                    assert!(stairs.remove(&p));
+                }
+            }
+            assert_eq!(stairs.len(), points.len());
+            if points.is_empty() {
+                assert_eq!(stairs.iter().count(), 0);
+            } else {
+                assert_eq!(stairs.iter().collect::<Vec<_>>(), staircase(&points));
+            }
+        }
+    }
+
+    #[test]
+    fn ut_dynamic_staircase_duplicates() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut stairs = DynamicStaircase::new();
+        let mut points: Vec<Point<f32>> = Vec::new();
+        for _ in 0..2_000 {
+            match points.is_empty() || rng.gen_bool(0.5) {
+                true => {
+                    let p = Point::from((rng.gen_range(0..8) as f32, rng.gen_range(0..8) as f32));
+                    stairs.insert(p);
+                    points.push(p);
+                }
+                false => {
+                    let p = points.swap_remove(rng.gen_range(0..points.len()));
+                    assert!(stairs.remove(&p));
+                }
+            }
+            // the maximal points are not dominated by any other point
+            let mut expected: Vec<Point<f32>> = points
+                .iter()
+                .filter(|p| {
+                    points
+                        .iter()
+                        .all(|q| q == *p || q.x() < p.x() || q.y() < p.y())
+                })
+                .copied()
+                .collect();
+            expected.sort_by_key(|p| Key(*p));
+            expected.dedup();
+            assert_eq!(stairs.iter().copied().collect::<Vec<_>>(), expected);
+        }
+    }
 }